@@ -0,0 +1,327 @@
+// State-transition alerting: tracks up/down state per check and notifies webhooks on changes
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::ResultRow;
+
+/// Payload layout expected by a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    Json,    // The alert itself as a JSON document
+    Slack,   // Slack incoming webhook ({"text": ...})
+    Teams,   // Microsoft Teams connector card
+    Discord, // Discord webhook ({"content": ...})
+}
+
+/// A webhook to deliver alerts to, given as `[FORMAT=]URL` on the command line
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub format: WebhookFormat, // Payload layout, JSON by default
+    pub url: String,           // Endpoint the payload is POSTed to
+}
+
+impl FromStr for Webhook {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (format, url) = match s.split_once('=') {
+            Some(("json", url)) => (WebhookFormat::Json, url),
+            Some(("slack", url)) => (WebhookFormat::Slack, url),
+            Some(("teams", url)) => (WebhookFormat::Teams, url),
+            Some(("discord", url)) => (WebhookFormat::Discord, url),
+            _ => (WebhookFormat::Json, s),
+        };
+        let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("invalid webhook URL '{}': {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(anyhow!("webhook URL must be http:// or https://, got '{}'", url));
+        }
+        Ok(Webhook { format, url: url.to_string() })
+    }
+}
+
+/// Kind of state change an alert reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    Down,      // The check failed often enough in a row to count as down
+    Recovered, // A down check succeeded often enough in a row to count as up again
+}
+
+/// A state change of one check
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub event: Event,                  // Down or recovered
+    pub url: String,                   // Checked URL, or "scenario:<name>"
+    pub status: String,                // Status of the check that triggered the alert
+    pub reason: String,                // Reason or error message of that check
+    pub consecutive: u32,              // Failures (down) or successes (recovered) in a row
+    pub down_since: Option<String>,    // When the check first failed, for recoveries
+    pub downtime_secs: Option<i64>,    // How long the check was down, for recoveries
+    pub timestamp: String,             // When the alert was raised
+    pub result: ResultRow,             // The result row that triggered the alert
+}
+
+/// Thresholds that suppress flapping
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub failures: u32,   // Consecutive failures before a check counts as down
+    pub recoveries: u32, // Consecutive successes before a down check counts as up
+}
+
+/// Up/down state of one check
+#[derive(Default)]
+struct CheckState {
+    failures: u32,                          // Current run of failures
+    successes: u32,                         // Current run of successes
+    down: bool,                             // Whether a down alert was raised and not yet recovered
+    first_failure: Option<DateTime<Utc>>,   // Start of the current run of failures
+    down_since: Option<DateTime<Utc>>,      // First failure of the current outage
+}
+
+/// Tracks check states and turns state changes into alerts
+/// Every check starts out as up, so a check that is down from the start raises an alert
+pub struct Alerter {
+    thresholds: Thresholds,
+    states: HashMap<String, CheckState>,
+}
+
+impl Alerter {
+    pub fn new(thresholds: Thresholds) -> Self {
+        Alerter { thresholds, states: HashMap::new() }
+    }
+
+    /// Records a check result and returns an alert if it changes the check's state
+    ///
+    /// # Arguments
+    /// * `key` - Identifies the check across cycles
+    /// * `row` - Latest result of the check
+    ///
+    /// # Returns
+    /// * `Option<Alert>` - Alert for a down or recovered transition, None otherwise
+    pub fn observe(&mut self, key: &str, row: &ResultRow) -> Option<Alert> {
+        // SKIPPED rows say nothing about the URL itself
+        if row.status == "SKIPPED" {
+            return None;
+        }
+        let now = Utc::now();
+        let state = self.states.entry(key.to_string()).or_default();
+        let alert = |event, consecutive, down_since: Option<DateTime<Utc>>| Alert {
+            event,
+            url: row.url.clone(),
+            status: row.status.clone(),
+            reason: if row.assertions.is_empty() { row.reason.clone() } else { row.assertions.clone() },
+            consecutive,
+            down_since: down_since.map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            downtime_secs: down_since.map(|t| (now - t).num_seconds()),
+            timestamp: now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            result: row.clone(),
+        };
+
+        if row.is_up() {
+            state.failures = 0;
+            state.first_failure = None;
+            state.successes += 1;
+            if state.down && state.successes >= self.thresholds.recoveries {
+                state.down = false;
+                let since = state.down_since.take();
+                return Some(alert(Event::Recovered, state.successes, since));
+            }
+        } else {
+            state.successes = 0;
+            state.failures += 1;
+            state.first_failure.get_or_insert(now);
+            if !state.down && state.failures >= self.thresholds.failures {
+                state.down = true;
+                state.down_since = state.first_failure;
+                return Some(alert(Event::Down, state.failures, None));
+            }
+        }
+        None
+    }
+}
+
+/// One-line human-readable description of an alert
+pub fn summary(alert: &Alert) -> String {
+    match alert.event {
+        Event::Down => format!(
+            "🔴 DOWN: {} - {} {} ({} consecutive failure(s))",
+            alert.url, alert.status, alert.reason, alert.consecutive
+        ),
+        Event::Recovered => format!(
+            "🟢 RECOVERED: {} - {} {}{}",
+            alert.url,
+            alert.status,
+            alert.reason,
+            alert
+                .downtime_secs
+                .map(|secs| format!(" (down for {})", crate::timeouts::format_duration(Duration::from_secs(secs.max(0) as u64))))
+                .unwrap_or_default()
+        ),
+    }
+}
+
+/// Builds the request body for a webhook
+///
+/// # Arguments
+/// * `format` - Payload layout of the webhook
+/// * `alert` - Alert to describe
+///
+/// # Returns
+/// * `serde_json::Value` - JSON payload
+pub fn payload(format: WebhookFormat, alert: &Alert) -> serde_json::Value {
+    let text = summary(alert);
+    match format {
+        WebhookFormat::Json => serde_json::to_value(alert).unwrap_or_default(),
+        WebhookFormat::Slack => serde_json::json!({ "text": text }),
+        WebhookFormat::Discord => serde_json::json!({ "content": text }),
+        WebhookFormat::Teams => serde_json::json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": text,
+            "themeColor": if alert.event == Event::Down { "D93F0B" } else { "2EB886" },
+            "title": text,
+            "sections": [{
+                "facts": [
+                    { "name": "URL", "value": alert.url },
+                    { "name": "Status", "value": alert.status },
+                    { "name": "Reason", "value": alert.reason },
+                    { "name": "Time", "value": alert.timestamp },
+                ]
+            }]
+        }),
+    }
+}
+
+/// Delivers alerts to every webhook, retrying failed deliveries with exponential backoff
+///
+/// # Arguments
+/// * `client` - Client used for the webhook requests
+/// * `webhooks` - Webhooks to notify
+/// * `alerts` - Alerts to deliver
+/// * `retries` - Additional attempts after a failed delivery
+///
+/// # Returns
+/// * `Vec<String>` - A message for every delivery that failed after all retries
+pub async fn deliver(client: &Client, webhooks: &[Webhook], alerts: &[Alert], retries: u32) -> Vec<String> {
+    let deliveries = webhooks.iter().flat_map(|webhook| {
+        alerts.iter().map(move |alert| async move {
+            let body = payload(webhook.format, alert);
+            send_with_retry(client, &webhook.url, &body, retries)
+                .await
+                .err()
+                .map(|e| format!("webhook {} failed for {}: {:#}", webhook.url, alert.url, e))
+        })
+    });
+    futures::future::join_all(deliveries).await.into_iter().flatten().collect()
+}
+
+/// POSTs a payload, retrying on connection errors and non-2xx responses
+async fn send_with_retry(client: &Client, url: &str, body: &serde_json::Value, retries: u32) -> Result<()> {
+    let mut delay = Duration::from_secs(1);
+    let mut attempt = 0;
+    loop {
+        let error = match client.post(url).json(body).send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => anyhow!("status {}", resp.status()),
+            Err(e) => anyhow!(e),
+        };
+        if attempt >= retries {
+            return Err(error.context(format!("giving up after {} attempt(s)", attempt + 1)));
+        }
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(status: &str) -> ResultRow {
+        ResultRow {
+            url: "http://example.com".to_string(),
+            status: status.to_string(),
+            reason: if status == "ERROR" { "connection refused".to_string() } else { String::new() },
+            ..Default::default()
+        }
+    }
+
+    /// Feeds a sequence of statuses to an alerter and returns the events it raised, by position
+    fn events(thresholds: Thresholds, statuses: &[&str]) -> Vec<(usize, Event, u32)> {
+        let mut alerter = Alerter::new(thresholds);
+        statuses
+            .iter()
+            .enumerate()
+            .filter_map(|(i, status)| alerter.observe("check", &row(status)).map(|a| (i, a.event, a.consecutive)))
+            .collect()
+    }
+
+    #[test]
+    fn a_check_is_down_after_the_failure_threshold() {
+        let thresholds = Thresholds { failures: 3, recoveries: 1 };
+        assert_eq!(events(thresholds, &["ERROR", "ERROR", "200", "ERROR", "ERROR"]), []);
+        assert_eq!(events(thresholds, &["ERROR", "500", "ERROR", "ERROR"]), [(2, Event::Down, 3)]);
+    }
+
+    #[test]
+    fn flapping_is_suppressed_until_enough_successes() {
+        let thresholds = Thresholds { failures: 1, recoveries: 2 };
+        let statuses = ["ERROR", "200", "ERROR", "200", "200", "200", "ERROR"];
+        assert_eq!(
+            events(thresholds, &statuses),
+            [(0, Event::Down, 1), (4, Event::Recovered, 2), (6, Event::Down, 1)]
+        );
+    }
+
+    #[test]
+    fn skipped_checks_keep_their_state() {
+        let thresholds = Thresholds { failures: 2, recoveries: 1 };
+        assert_eq!(events(thresholds, &["ERROR", "SKIPPED", "ERROR"]), [(2, Event::Down, 2)]);
+    }
+
+    #[test]
+    fn recoveries_report_the_outage() {
+        let mut alerter = Alerter::new(Thresholds { failures: 1, recoveries: 1 });
+        let down = alerter.observe("check", &row("ERROR")).unwrap();
+        assert_eq!(down.down_since, None);
+        assert_eq!(down.reason, "connection refused");
+
+        let recovered = alerter.observe("check", &row("200")).unwrap();
+        assert_eq!(recovered.event, Event::Recovered);
+        assert!(recovered.down_since.is_some());
+        assert!(recovered.downtime_secs.unwrap() >= 0);
+        // Other checks have their own state
+        assert!(alerter.observe("other", &row("200")).is_none());
+    }
+
+    #[test]
+    fn payloads_match_the_webhook_format() {
+        let mut alerter = Alerter::new(Thresholds { failures: 1, recoveries: 1 });
+        let alert = alerter.observe("check", &row("ERROR")).unwrap();
+        let text = "🔴 DOWN: http://example.com - ERROR connection refused (1 consecutive failure(s))";
+
+        assert_eq!(payload(WebhookFormat::Slack, &alert), serde_json::json!({ "text": text }));
+        assert_eq!(payload(WebhookFormat::Discord, &alert), serde_json::json!({ "content": text }));
+
+        let teams = payload(WebhookFormat::Teams, &alert);
+        assert_eq!(teams["@type"], "MessageCard");
+        assert_eq!(teams["title"], text);
+        assert_eq!(teams["themeColor"], "D93F0B");
+        assert_eq!(teams["sections"][0]["facts"][0], serde_json::json!({ "name": "URL", "value": "http://example.com" }));
+
+        let json = payload(WebhookFormat::Json, &alert);
+        assert_eq!(json["event"], "down");
+        assert_eq!(json["result"]["status"], "ERROR");
+
+        let recovered = alerter.observe("check", &row("200")).unwrap();
+        assert_eq!(payload(WebhookFormat::Teams, &recovered)["themeColor"], "2EB886");
+        assert!(payload(WebhookFormat::Slack, &recovered)["text"].as_str().unwrap().starts_with("🟢 RECOVERED: http://example.com - 200"));
+    }
+}
@@ -0,0 +1,285 @@
+// Classification of failed checks into error categories for breakdowns and counters
+use std::error::Error as _;
+use std::io::ErrorKind;
+
+use crate::ResultRow;
+
+/// Why a check failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Dns,        // Host name could not be resolved
+    Connect,    // Connection refused, reset or unreachable
+    Tls,        // TLS handshake or certificate failure
+    Timeout,    // A connect, first-byte, read or total timeout expired
+    Http4xx,    // Client error status
+    Http5xx,    // Server error status
+    HttpOther,  // Any other unexpected status
+    Assertion,  // Response arrived but failed an assertion
+    Deadline,   // Skipped because the run deadline was reached
+    Other,      // Anything else (invalid URL, template error, body read error, ...)
+}
+
+impl Category {
+    /// Every category, in display order
+    pub const ALL: [Category; 10] = [
+        Category::Dns,
+        Category::Connect,
+        Category::Tls,
+        Category::Timeout,
+        Category::Http4xx,
+        Category::Http5xx,
+        Category::HttpOther,
+        Category::Assertion,
+        Category::Deadline,
+        Category::Other,
+    ];
+
+    /// Looks a category up by its machine-readable name
+    pub fn from_name(name: &str) -> Option<Category> {
+        Category::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// Machine-readable name used in JSON reports and metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Dns => "dns",
+            Category::Connect => "connect",
+            Category::Tls => "tls",
+            Category::Timeout => "timeout",
+            Category::Http4xx => "http_4xx",
+            Category::Http5xx => "http_5xx",
+            Category::HttpOther => "http_other",
+            Category::Assertion => "assertion",
+            Category::Deadline => "deadline",
+            Category::Other => "other",
+        }
+    }
+
+    /// Human-readable label
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::Dns => "DNS resolution",
+            Category::Connect => "Connection",
+            Category::Tls => "TLS / certificate",
+            Category::Timeout => "Timeout",
+            Category::Http4xx => "HTTP 4xx",
+            Category::Http5xx => "HTTP 5xx",
+            Category::HttpOther => "Unexpected status",
+            Category::Assertion => "Assertion",
+            Category::Deadline => "Run deadline",
+            Category::Other => "Other",
+        }
+    }
+}
+
+/// Classifies a result row
+/// Failed scenario summary rows are `Other`; the failing step row carries the actual cause
+/// Errors use the category recorded from the error when the row was created; rows read
+/// back from the history fall back to the error message, with the URL taken out so a host
+/// or path like `ssl-timeout.example.com/connect` cannot decide the category
+///
+/// # Arguments
+/// * `row` - Result row to classify
+///
+/// # Returns
+/// * `Option<Category>` - Category of a failed check, None for successful checks
+pub fn of(row: &ResultRow) -> Option<Category> {
+    if row.is_up() {
+        return None;
+    }
+    if row.is_scenario_summary() {
+        return Some(Category::Other);
+    }
+    if row.status == "SKIPPED" {
+        return Some(Category::Deadline);
+    }
+    if !row.assertions.is_empty() {
+        return Some(Category::Assertion);
+    }
+    if row.status.starts_with('4') {
+        return Some(Category::Http4xx);
+    }
+    if row.status.starts_with('5') {
+        return Some(Category::Http5xx);
+    }
+    if row.status != "ERROR" {
+        return Some(Category::HttpOther);
+    }
+    if let Some(category) = row.category {
+        return Some(category);
+    }
+
+    let reason = strip_url(&row.reason, &row.url).to_lowercase();
+    let category = if reason.contains("timeout") || reason.contains("timed out") {
+        Category::Timeout
+    } else if reason.contains("dns error") || reason.contains("failed to lookup") || reason.contains("name or service not known") {
+        Category::Dns
+    } else if reason.contains("certificate") || reason.contains("tls") || reason.contains("ssl") || reason.contains("handshake") {
+        Category::Tls
+    } else if reason.contains("connect") || reason.contains("connection") || reason.contains("unreachable") {
+        Category::Connect
+    } else {
+        Category::Other
+    };
+    Some(category)
+}
+
+/// Classifies a request error from its kind and the errors it was caused by
+/// hyper and the OS report connection failures as typed I/O errors; rustls failures arrive
+/// as `InvalidData` I/O errors while connecting; failed lookups as hyper's "dns error"
+///
+/// # Arguments
+/// * `error` - Error of sending a request or reading its body
+///
+/// # Returns
+/// * `Category` - Category of the failure
+pub fn of_error(error: &reqwest::Error) -> Category {
+    if error.is_timeout() {
+        return Category::Timeout;
+    }
+    let mut cause = error.source();
+    while let Some(err) = cause {
+        if err.to_string().starts_with("dns error") {
+            return Category::Dns;
+        }
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            match io.kind() {
+                ErrorKind::TimedOut => return Category::Timeout,
+                ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::NotConnected
+                | ErrorKind::AddrNotAvailable
+                | ErrorKind::HostUnreachable
+                | ErrorKind::NetworkUnreachable => return Category::Connect,
+                ErrorKind::InvalidData if error.is_connect() => return Category::Tls,
+                _ => {}
+            }
+            // An I/O error's source() skips the error it wraps, so step into it directly
+            if let Some(inner) = io.get_ref() {
+                cause = Some(inner);
+                continue;
+            }
+        }
+        cause = err.source();
+    }
+    if error.is_connect() {
+        Category::Connect
+    } else {
+        Category::Other
+    }
+}
+
+/// Removes the URL from an error message: reqwest quotes the request URL as "for url (...)",
+/// and the checked URL may appear on its own
+fn strip_url(reason: &str, url: &str) -> String {
+    let mut reason = reason.to_string();
+    if let Some(start) = reason.find("for url (") {
+        if let Some(end) = reason[start..].find("): ") {
+            reason.replace_range(start..start + end + 1, "");
+        }
+    }
+    if !url.is_empty() {
+        reason = reason.replace(url, "");
+    }
+    reason
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn row(status: &str, reason: &str) -> ResultRow {
+        ResultRow {
+            url: "https://ssl-timeout.example.com/connect".to_string(),
+            status: status.to_string(),
+            reason: reason.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Error of a GET to `url`
+    async fn error(url: &str) -> reqwest::Error {
+        reqwest::Client::new()
+            .get(url)
+            .timeout(Duration::from_millis(500))
+            .send()
+            .await
+            .unwrap_err()
+    }
+
+    #[test]
+    fn statuses_and_row_kinds() {
+        assert_eq!(of(&row("200", "OK")), None);
+        assert_eq!(of(&row("404", "Not Found")), Some(Category::Http4xx));
+        assert_eq!(of(&row("503", "Service Unavailable")), Some(Category::Http5xx));
+        assert_eq!(of(&row("101", "Switching Protocols")), Some(Category::HttpOther));
+        assert_eq!(of(&row("SKIPPED", "cancelled: run deadline reached")), Some(Category::Deadline));
+        let asserted = ResultRow { assertions: "expected body to contain 'ok'".to_string(), ..row("200", "OK") };
+        assert_eq!(of(&asserted), Some(Category::Assertion));
+        let summary = ResultRow { scenario: "checkout".to_string(), ..row("FAIL", "pay failed: 500") };
+        assert_eq!(of(&summary), Some(Category::Other));
+    }
+
+    #[test]
+    fn recorded_category_wins_over_the_reason() {
+        let errored = ResultRow { category: Some(Category::Connect), ..row("ERROR", "timed out somewhere") };
+        assert_eq!(of(&errored), Some(Category::Connect));
+    }
+
+    #[test]
+    fn reason_fallback_ignores_the_url() {
+        let prefix = "error sending request for url (https://ssl-timeout.example.com/connect): error trying to connect: ";
+        let cases = [
+            ("tcp connect error: Connection refused (os error 111)", Category::Connect),
+            ("dns error: failed to lookup address information: Name or service not known", Category::Dns),
+            ("invalid peer certificate: UnknownIssuer", Category::Tls),
+            ("received corrupt message of type InvalidContentType", Category::Connect),
+        ];
+        for (cause, expected) in cases {
+            assert_eq!(of(&row("ERROR", &format!("{}{}", prefix, cause))), Some(expected), "{}", cause);
+        }
+        assert_eq!(of(&row("ERROR", "read timeout: no data for 5s after 0 bytes")), Some(Category::Timeout));
+        assert_eq!(of(&row("ERROR", "https://ssl-timeout.example.com/connect is broken")), Some(Category::Other));
+    }
+
+    #[tokio::test]
+    async fn refused_connection_is_connect() {
+        // Bind and drop a listener so the port is known to be closed
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        assert_eq!(of_error(&error(&format!("http://127.0.0.1:{}/ssl-timeout", port)).await), Category::Connect);
+    }
+
+    #[tokio::test]
+    async fn failed_lookup_is_dns() {
+        assert_eq!(of_error(&error("http://tls-timeout.invalid/connect").await), Category::Dns);
+    }
+
+    #[tokio::test]
+    async fn tls_to_a_plain_listener_is_tls() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n").await;
+            }
+        });
+        assert_eq!(of_error(&error(&format!("https://127.0.0.1:{}/timeout", port)).await), Category::Tls);
+    }
+
+    #[tokio::test]
+    async fn silent_server_is_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+        assert_eq!(of_error(&error(&format!("http://127.0.0.1:{}/connect-tls-dns", port)).await), Category::Timeout);
+    }
+}
@@ -0,0 +1,90 @@
+// Configuration file (JSON) for settings that are easier to keep in a file than on the command line
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::otel::OtlpConfig;
+use crate::sinks::SinkSpec;
+
+/// Contents of the file given with --config
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub sinks: Vec<SinkSpec>, // Metric sinks, used together with those given by --sink
+    #[serde(default)]
+    pub otlp: OtlpConfig,     // OpenTelemetry export; command-line options take precedence
+}
+
+/// Reads and validates a configuration file
+///
+/// # Arguments
+/// * `path` - JSON configuration file
+///
+/// # Returns
+/// * `Result<Config>` - Parsed configuration, or an error naming the file and the problem
+pub fn load(path: &str) -> Result<Config> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Could not read config file {}", path))?;
+    let config: Config = serde_json::from_str(&text).with_context(|| format!("Invalid config file {}", path))?;
+    for sink in &config.sinks {
+        sink.validate().with_context(|| format!("Invalid sink in config file {}", path))?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::SinkKind;
+
+    /// Loads `text` from a temporary config file
+    fn load_text(name: &str, text: &str) -> Result<Config> {
+        let path = std::env::temp_dir().join(format!("url-checker-config-{}-{}.json", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        let config = load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn sinks_and_otlp() {
+        let config = load_text(
+            "full",
+            r#"{
+                "sinks": [
+                    {"type": "influx", "target": "http://localhost:8086/api/v2/write", "token": "t", "tags": {"env": "prod"}},
+                    {"type": "statsd", "target": "localhost:8125", "prefix": "web"}
+                ],
+                "otlp": {"endpoint": "http://localhost:4318", "headers": {"x-api-key": "k"}, "traceparent": true}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.sinks.len(), 2);
+        assert_eq!(config.sinks[0].kind, SinkKind::Influx);
+        assert_eq!(config.sinks[0].tags["env"], "prod");
+        assert_eq!(config.sinks[1].prefix.as_deref(), Some("web"));
+        assert_eq!(config.otlp.endpoint.as_deref(), Some("http://localhost:4318"));
+        assert!(config.otlp.traceparent);
+
+        let empty = load_text("empty", "{}").unwrap();
+        assert!(empty.sinks.is_empty() && empty.otlp.endpoint.is_none());
+    }
+
+    #[test]
+    fn errors_name_the_problem() {
+        let error = |name: &str, text: &str| format!("{:#}", load_text(name, text).unwrap_err());
+
+        assert!(error("unknown", r#"{"sink": []}"#).contains("unknown field `sink`"));
+        assert!(error("unknown-sink-key", r#"{"sinks": [{"type": "statsd", "target": "h:1", "port": 1}]}"#)
+            .contains("unknown field `port`"));
+        assert!(error("unknown-otlp-key", r#"{"otlp": {"url": "http://localhost:4318"}}"#).contains("unknown field `url`"));
+        assert!(error("kind", r#"{"sinks": [{"type": "carbon", "target": "h:1"}]}"#).contains("unknown variant `carbon`"));
+        assert!(error("syntax", r#"{"sinks": ["#).contains("Invalid config file"));
+        assert!(error("target", r#"{"sinks": [{"type": "graphite", "target": "localhost"}]}"#)
+            .contains("graphite sink needs HOST:PORT"));
+        assert!(error("tags", r#"{"sinks": [{"type": "statsd", "target": "h:1", "tags": {"a": "b"}}]}"#)
+            .contains("only apply to influx sinks"));
+
+        let missing = format!("{:#}", load("/nonexistent/url-checker.json").unwrap_err());
+        assert!(missing.contains("Could not read config file /nonexistent/url-checker.json"));
+    }
+}
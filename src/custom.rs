@@ -0,0 +1,98 @@
+// User-defined report templates rendered with Tera (Jinja2-like syntax)
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::{category, ResultRow};
+
+/// A report template loaded from a file
+pub struct Template {
+    tera: tera::Tera,
+    name: String,
+}
+
+impl Template {
+    /// Reads and parses a template file
+    /// Templates ending in .html, .htm or .xml escape HTML in substituted values
+    ///
+    /// # Arguments
+    /// * `path` - Template file
+    ///
+    /// # Returns
+    /// * `Result<Template>` - Parsed template, or an error pointing at the syntax problem
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Could not read template {}", path))?;
+        let mut tera = tera::Tera::default();
+        tera.autoescape_on(vec![".html", ".htm", ".xml"]);
+        tera.add_raw_template(path, &text)
+            .with_context(|| format!("Invalid template {}", path))?;
+        Ok(Template { tera, name: path.to_string() })
+    }
+
+    /// Renders the template
+    ///
+    /// # Arguments
+    /// * `metadata` - Report metadata (statistics), as in the JSON report
+    /// * `rows` - Result rows of the run
+    ///
+    /// # Returns
+    /// * `Result<String>` - Rendered report, or an error naming the failing expression
+    pub fn render(&self, metadata: &Value, rows: &[ResultRow]) -> Result<String> {
+        let context = tera::Context::from_value(context(metadata, rows)?)?;
+        self.tera
+            .render(&self.name, &context)
+            .with_context(|| format!("Could not render template {}", self.name))
+    }
+}
+
+/// Builds the variables a template sees:
+/// * `stats` - the JSON report's `metadata` block
+/// * `results` - every row, with the extra fields `up`, `category` and `host`
+/// * `failures` - rows of failed checks
+/// * `by_tag`, `by_host`, `by_status`, `by_category` - rows grouped by tag, host,
+///   status and error category (sorted by key)
+/// * `generated_at` - time the report was generated
+fn context(metadata: &Value, rows: &[ResultRow]) -> Result<Value> {
+    let mut results = Vec::with_capacity(rows.len());
+    let mut by_tag: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut by_host: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut by_status: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut by_category: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+
+    for row in rows {
+        let host = reqwest::Url::parse(&row.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let category = category::of(row).map(|c| c.name());
+        let mut value = serde_json::to_value(row)?;
+        value["up"] = row.is_up().into();
+        value["category"] = category.into();
+        value["host"] = host.clone().into();
+
+        for tag in row.tags.split(',').filter(|t| !t.is_empty()) {
+            by_tag.entry(tag.to_string()).or_default().push(value.clone());
+        }
+        if !host.is_empty() {
+            by_host.entry(host).or_default().push(value.clone());
+        }
+        by_status.entry(row.status.clone()).or_default().push(value.clone());
+        if let Some(category) = category {
+            by_category.entry(category.to_string()).or_default().push(value.clone());
+        }
+        results.push(value);
+    }
+
+    let failures: Vec<&Value> = results.iter().filter(|r| r["up"] == false).collect();
+    Ok(serde_json::json!({
+        "stats": metadata,
+        "results": results,
+        "failures": failures,
+        "by_tag": by_tag,
+        "by_host": by_host,
+        "by_status": by_status,
+        "by_category": by_category,
+        "generated_at": metadata["generated_at"],
+    }))
+}
@@ -0,0 +1,578 @@
+// Comparison of two reports: checks that broke, recovered, got slower or changed status or redirect target
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::Args as ClapArgs;
+use colored::*;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{markdown, output, timeouts, ResultRow};
+
+/// Options of the `diff` subcommand
+#[derive(ClapArgs, Debug)]
+pub struct DiffArgs {
+    /// Earlier report (CSV, JSON or NDJSON as written by --format/--report)
+    pub old: String,
+
+    /// Later report to compare with it
+    pub new: String,
+
+    /// Slowdown relative to the earlier response time that counts as significant, in percent
+    #[arg(long, default_value_t = 50.0)]
+    pub slower_percent: f64,
+
+    /// Smallest slowdown that counts as significant, so jitter on fast URLs is ignored
+    #[arg(long, default_value = "100ms", value_parser = timeouts::parse_duration)]
+    pub slower_by: Duration,
+
+    /// Output format: table, csv, json or markdown
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: DiffFormat,
+
+    /// File to write the differences to (default: stdout)
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+/// Output format of the differences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiffFormat {
+    Table,    // Colored terminal summary
+    Csv,      // One row per change
+    Json,     // Changes and counts as one document
+    Markdown, // Tables per kind of change, for PR comments
+}
+
+/// Kind of change between the two reports, in the order they are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Broke,           // Up before, down now
+    Slower,          // Up in both, and significantly slower now
+    Recovered,       // Down before, up now
+    StatusChanged,   // Different status without changing between up and down
+    RedirectChanged, // Redirected to a different URL (or no longer/newly redirected)
+    Added,           // Only in the later report
+    Removed,         // Only in the earlier report
+}
+
+impl Change {
+    /// Name of the change in JSON output, as serialized
+    fn id(&self) -> &'static str {
+        match self {
+            Change::Broke => "broke",
+            Change::Slower => "slower",
+            Change::Recovered => "recovered",
+            Change::StatusChanged => "status_changed",
+            Change::RedirectChanged => "redirect_changed",
+            Change::Added => "added",
+            Change::Removed => "removed",
+        }
+    }
+
+    /// Heading of the change in the table and Markdown output
+    fn label(&self) -> &'static str {
+        match self {
+            Change::Broke => "Newly broken",
+            Change::Slower => "Significantly slower",
+            Change::Recovered => "Recovered",
+            Change::StatusChanged => "Status changed",
+            Change::RedirectChanged => "Redirect target changed",
+            Change::Added => "Added",
+            Change::Removed => "Removed",
+        }
+    }
+
+    /// Whether the change counts as a regression, making `diff` exit with status 2
+    fn is_regression(&self) -> bool {
+        matches!(self, Change::Broke | Change::Slower)
+    }
+}
+
+/// One change of one check
+#[derive(Debug, Serialize)]
+pub struct Delta {
+    pub change: Change,              // Kind of change
+    pub url: String,                 // Checked URL, or "scenario:<name>"
+    pub scenario: String,            // Scenario the row belongs to, empty for URL checks
+    pub step: String,                // Scenario step, empty for URL checks and scenario summaries
+    pub pinned_to: String,           // Address or family the check was pinned to, e.g. in dual-stack runs
+    #[serde(skip)]
+    pub fanned_out: bool,            // Whether the URL was checked over several addresses or families
+    pub old_status: String,          // Status in the earlier report, empty if added
+    pub new_status: String,          // Status in the later report, empty if removed
+    pub old_time_ms: Option<u128>,   // Response time in the earlier report, None without a response
+    pub new_time_ms: Option<u128>,   // Response time in the later report, None without a response
+    pub old_final_url: String,       // Redirect target in the earlier report
+    pub new_final_url: String,       // Redirect target in the later report
+    pub detail: String,              // Reason of a failure or size of a slowdown
+}
+
+/// Rows of one report
+struct Report {
+    rows: Vec<ResultRow>, // Latest row of every check, in order of first appearance
+    final_urls: bool,     // Whether the report records redirect targets (older reports do not)
+}
+
+/// Runs the `diff` subcommand
+///
+/// # Arguments
+/// * `args` - Reports to compare, slowdown thresholds and output settings
+///
+/// # Returns
+/// * `Result<bool>` - Whether there are regressions, or an error reading a report
+pub fn run(args: &DiffArgs) -> Result<bool> {
+    if args.slower_percent < 0.0 {
+        bail!("--slower-percent must not be negative, got {}", args.slower_percent);
+    }
+    let old = load(&args.old)?;
+    let new = load(&args.new)?;
+    let (deltas, unchanged) = compare(&old, &new, args);
+
+    let mut out = output::create(args.output.as_deref().unwrap_or("-"))?;
+    match args.format {
+        DiffFormat::Table => print_table(&mut out, args, &deltas, unchanged)?,
+        DiffFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(out);
+            for delta in &deltas {
+                wtr.serialize(delta)?;
+            }
+            wtr.flush()?;
+        }
+        DiffFormat::Json => {
+            let document = serde_json::json!({
+                "old": args.old,
+                "new": args.new,
+                "counts": counts(&deltas).into_iter().map(|(c, n)| (c.id().to_string(), Value::from(n))).collect::<serde_json::Map<_, _>>(),
+                "unchanged": unchanged,
+                "regressions": deltas.iter().filter(|d| d.change.is_regression()).count(),
+                "changes": deltas,
+            });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        DiffFormat::Markdown => write!(out, "{}", render_markdown(args, &deltas, unchanged))?,
+    }
+    Ok(deltas.iter().any(|d| d.change.is_regression()))
+}
+
+/// Reads a CSV, JSON or NDJSON report, detected by extension or, failing that, content
+/// JSON watch output (one line per cycle) and appended CSV reports hold several rows per
+/// check; the last one counts, per family or address for URLs pinned to several
+fn load(path: &str) -> Result<Report> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Could not read report {}", path))?;
+    let lower = path.to_ascii_lowercase();
+    let json = if lower.ends_with(".csv") {
+        false
+    } else if lower.ends_with(".json") || lower.ends_with(".ndjson") || lower.ends_with(".jsonl") {
+        true
+    } else {
+        text.trim_start().starts_with('{')
+    };
+    let (rows, final_urls) = if json { load_json(&text) } else { load_csv(&text) }
+        .with_context(|| format!("Could not parse report {}", path))?;
+
+    // Keep the latest row of every check
+    let mut index: HashMap<Key, usize> = HashMap::new();
+    let mut latest: Vec<ResultRow> = Vec::new();
+    for row in rows {
+        match index.get(&key(&row)) {
+            Some(&i) => latest[i] = row,
+            None => {
+                index.insert(key(&row), latest.len());
+                latest.push(row);
+            }
+        }
+    }
+    Ok(Report { rows: latest, final_urls })
+}
+
+/// Parses CSV rows
+fn load_csv(text: &str) -> Result<(Vec<ResultRow>, bool)> {
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    let headers = rdr.headers()?.clone();
+    if !headers.iter().any(|h| h == "url") || !headers.iter().any(|h| h == "status") {
+        bail!("not a url-checker CSV report (no url and status columns)");
+    }
+    let final_urls = headers.iter().any(|h| h == "final_url");
+    let mut rows = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        // Watch mode writes a header again when appending to a file that was emptied
+        if record.get(0) == Some("url") {
+            continue;
+        }
+        rows.push(record.deserialize(Some(&headers))?);
+    }
+    Ok((rows, final_urls))
+}
+
+/// Parses a JSON report (`{"metadata", "results"}`), JSON watch output (one
+/// `{"cycle", "results"}` object per line) or NDJSON (one `"type": "result"` object per line)
+fn load_json(text: &str) -> Result<(Vec<ResultRow>, bool)> {
+    let documents: Vec<Value> = match serde_json::from_str::<Value>(text) {
+        Ok(document) => vec![document],
+        Err(_) => text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .enumerate()
+            .map(|(i, l)| serde_json::from_str(l).with_context(|| format!("line {}", i + 1)))
+            .collect::<Result<_>>()?,
+    };
+
+    let mut values: Vec<Value> = Vec::new();
+    for document in documents {
+        match document {
+            Value::Object(mut object) => {
+                if let Some(Value::Array(results)) = object.remove("results") {
+                    values.extend(results);
+                } else if object.get("type").and_then(Value::as_str) == Some("result") {
+                    values.push(Value::Object(object));
+                }
+            }
+            Value::Array(results) => values.extend(results),
+            _ => {}
+        }
+    }
+    if values.is_empty() {
+        bail!("no results found (expected a JSON or NDJSON report)");
+    }
+    let final_urls = values.iter().any(|v| v.get("final_url").is_some());
+    let rows = values
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<serde_json::Result<Vec<ResultRow>>>()?;
+    Ok((rows, final_urls))
+}
+
+/// Identity of a check within a report: URL, scenario, step and what the check was pinned to,
+/// so the rows of dual-stack and `--each-address` runs stay apart while a URL whose
+/// connection failed or moved to another address stays one check
+type Key = (String, String, String, String);
+
+fn key(row: &ResultRow) -> Key {
+    let (url, scenario, step) = check(row);
+    (url, scenario, step, row.pinned_to.clone())
+}
+
+/// Identity of a check regardless of what it was pinned to
+fn check(row: &ResultRow) -> (String, String, String) {
+    (row.url.clone(), row.scenario.clone(), row.step.clone())
+}
+
+/// Pairs the rows of the two reports: rows with the same key first, then rows of a URL pinned
+/// differently (e.g. a report without the `pinned_to` column) when they are the only unpaired
+/// row of their URL on both sides
+///
+/// # Returns
+/// * `Vec<(Option<&ResultRow>, Option<&ResultRow>)>` - Earlier and later row, in the order of the
+///   later report followed by rows only in the earlier one
+fn pair<'a>(old: &'a [ResultRow], new: &'a [ResultRow]) -> Vec<(Option<&'a ResultRow>, Option<&'a ResultRow>)> {
+    let index: HashMap<Key, usize> = old.iter().enumerate().map(|(i, r)| (key(r), i)).collect();
+    let mut matched: Vec<Option<usize>> = new.iter().map(|r| index.get(&key(r)).copied()).collect();
+    let mut used: HashSet<usize> = matched.iter().flatten().copied().collect();
+
+    let mut unpaired_old: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, row) in old.iter().enumerate().filter(|(i, _)| !used.contains(i)) {
+        unpaired_old.entry(check(row)).or_default().push(i);
+    }
+    let mut unpaired_new: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, row) in new.iter().enumerate().filter(|(i, _)| matched[*i].is_none()) {
+        unpaired_new.entry(check(row)).or_default().push(i);
+    }
+    for (check, news) in &unpaired_new {
+        if let (Some([o]), [n]) = (unpaired_old.get(check).map(Vec::as_slice), news.as_slice()) {
+            matched[*n] = Some(*o);
+            used.insert(*o);
+        }
+    }
+
+    let mut pairs: Vec<_> = new.iter().zip(&matched).map(|(row, m)| (m.map(|i| &old[i]), Some(row))).collect();
+    pairs.extend(old.iter().enumerate().filter(|(i, _)| !used.contains(i)).map(|(_, row)| (Some(row), None)));
+    pairs
+}
+
+/// Lists the changes between two reports, grouped by kind in the order of the later report
+///
+/// # Returns
+/// * `(Vec<Delta>, usize)` - Changes, and the number of checks in both reports without any
+fn compare(old: &Report, new: &Report, args: &DiffArgs) -> (Vec<Delta>, usize) {
+    let time = |row: &ResultRow| row.is_timed().then_some(row.time_ms);
+    let reason = |row: &ResultRow| if row.assertions.is_empty() { row.reason.clone() } else { row.assertions.clone() };
+
+    // Checks with several rows in either report are told apart by what they were pinned to
+    let mut fanned_out = HashSet::new();
+    for rows in [&old.rows, &new.rows] {
+        let mut seen = HashSet::new();
+        fanned_out.extend(rows.iter().map(check).filter(|c| !seen.insert(c.clone())));
+    }
+
+    let mut deltas = Vec::new();
+    let mut push = |change: Change, old: Option<&ResultRow>, new: Option<&ResultRow>, detail: String| {
+        let row = new.or(old).expect("a delta has at least one row");
+        deltas.push(Delta {
+            change,
+            url: row.url.clone(),
+            scenario: row.scenario.clone(),
+            step: row.step.clone(),
+            pinned_to: row.pinned_to.clone(),
+            fanned_out: fanned_out.contains(&check(row)),
+            old_status: old.map(|r| r.status.clone()).unwrap_or_default(),
+            new_status: new.map(|r| r.status.clone()).unwrap_or_default(),
+            old_time_ms: old.and_then(time),
+            new_time_ms: new.and_then(time),
+            old_final_url: old.map(|r| r.final_url.clone()).unwrap_or_default(),
+            new_final_url: new.map(|r| r.final_url.clone()).unwrap_or_default(),
+            detail,
+        });
+    };
+
+    let mut unchanged = 0;
+    for pair in pair(&old.rows, &new.rows) {
+        let (previous, row) = match pair {
+            (Some(previous), Some(row)) => (previous, row),
+            (None, Some(row)) => {
+                push(Change::Added, None, Some(row), String::new());
+                continue;
+            }
+            (Some(previous), None) => {
+                push(Change::Removed, Some(previous), None, String::new());
+                continue;
+            }
+            (None, None) => continue,
+        };
+        let mut changed = true;
+        match (previous.is_up(), row.is_up()) {
+            (true, false) => push(Change::Broke, Some(previous), Some(row), reason(row)),
+            (false, true) => push(Change::Recovered, Some(previous), Some(row), String::new()),
+            _ if previous.status != row.status => push(Change::StatusChanged, Some(previous), Some(row), String::new()),
+            _ => changed = false,
+        }
+        if let (true, true, Some(was), Some(now)) = (previous.is_up(), row.is_up(), time(previous), time(row)) {
+            let slower = now.saturating_sub(was);
+            let limit = (was as f64 * args.slower_percent / 100.0).max(args.slower_by.as_millis() as f64);
+            if slower > 0 && slower as f64 >= limit {
+                let percent = if was > 0 { format!(" (+{:.0}%)", slower as f64 / was as f64 * 100.0) } else { String::new() };
+                push(Change::Slower, Some(previous), Some(row), format!("+{} ms{}", slower, percent));
+                changed = true;
+            }
+        }
+        // Redirect targets only count when both reports record them and both checks got a response
+        if old.final_urls && new.final_urls && time(previous).is_some() && time(row).is_some() && previous.final_url != row.final_url {
+            push(Change::RedirectChanged, Some(previous), Some(row), String::new());
+            changed = true;
+        }
+        if !changed {
+            unchanged += 1;
+        }
+    }
+    deltas.sort_by_key(|d| d.change);
+    (deltas, unchanged)
+}
+
+/// Number of changes of every kind that occurs
+fn counts(deltas: &[Delta]) -> Vec<(Change, usize)> {
+    let mut counts: Vec<(Change, usize)> = Vec::new();
+    for delta in deltas {
+        match counts.last_mut() {
+            Some((change, n)) if *change == delta.change => *n += 1,
+            _ => counts.push((delta.change, 1)),
+        }
+    }
+    counts
+}
+
+/// Name of the check a delta is about: the URL, or scenario and step for scenario steps,
+/// followed by what it was pinned to when the URL was checked over several families or addresses
+fn name(delta: &Delta) -> String {
+    let mut name = if delta.step.is_empty() {
+        delta.url.clone()
+    } else {
+        format!("{} › {} ({})", delta.scenario, delta.step, delta.url)
+    };
+    if delta.fanned_out && !delta.pinned_to.is_empty() {
+        name.push_str(&format!(" [{}]", delta.pinned_to));
+    }
+    name
+}
+
+/// Status, time and redirect target on one side of a change, e.g. "200 · 120 ms"
+fn side(status: &str, time_ms: Option<u128>, final_url: &str, change: Change) -> String {
+    if status.is_empty() {
+        return "-".to_string();
+    }
+    let mut text = status.to_string();
+    if let Some(ms) = time_ms {
+        text.push_str(&format!(" · {} ms", ms));
+    }
+    if change == Change::RedirectChanged {
+        let target = if final_url.is_empty() { "not redirected" } else { final_url };
+        text.push_str(&format!(" · {}", target));
+    }
+    text
+}
+
+/// Writes the colored terminal summary
+fn print_table(out: &mut impl Write, args: &DiffArgs, deltas: &[Delta], unchanged: usize) -> Result<()> {
+    writeln!(out, "{} {} {} {}", "REPORT DIFF".bright_cyan().bold(), args.old.bright_white(), "→".bright_black(), args.new.bright_white())?;
+    writeln!(out, "{}", "═".repeat(100).bright_blue())?;
+    let counts = counts(deltas);
+    for (change, count) in &counts {
+        let heading = format!("{} ({})", change.label().to_uppercase(), count);
+        let (icon, heading) = match change {
+            Change::Broke => ("✗".red(), heading.red().bold()),
+            Change::Slower => ("⏱".yellow(), heading.yellow().bold()),
+            Change::Recovered => ("✓".green(), heading.green().bold()),
+            Change::Added | Change::Removed => ("•".bright_black(), heading.bold()),
+            Change::StatusChanged | Change::RedirectChanged => ("↻".cyan(), heading.cyan().bold()),
+        };
+        writeln!(out, "{} {}", icon, heading)?;
+        for delta in deltas.iter().filter(|d| d.change == *change) {
+            let before = side(&delta.old_status, delta.old_time_ms, &delta.old_final_url, delta.change);
+            let after = side(&delta.new_status, delta.new_time_ms, &delta.new_final_url, delta.change);
+            let detail = if delta.detail.is_empty() { String::new() } else { format!("  {}", delta.detail) };
+            writeln!(out, "  {:<60} {} {} {}{}", name(delta), before, "→".bright_black(), after, detail.bright_black())?;
+        }
+        writeln!(out, "{}", "─".repeat(100).bright_black())?;
+    }
+    let regressions = deltas.iter().filter(|d| d.change.is_regression()).count();
+    let summary = counts
+        .iter()
+        .map(|(change, count)| format!("{} {}", count, change.label().to_lowercase()))
+        .chain(std::iter::once(format!("{} unchanged", unchanged)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    writeln!(out, "{} {}", "ℹ".cyan(), summary)?;
+    if regressions > 0 {
+        writeln!(out, "{} {}", "✗".red(), format!("{} regression(s)", regressions).red().bold())?;
+    } else {
+        writeln!(out, "{} {}", "✓".green(), "No regressions".green().bold())?;
+    }
+    Ok(())
+}
+
+/// Renders the changes as Markdown, one table per kind of change
+fn render_markdown(args: &DiffArgs, deltas: &[Delta], unchanged: usize) -> String {
+    let regressions = deltas.iter().filter(|d| d.change.is_regression()).count();
+    let mut md = format!("## Report diff: `{}` → `{}`\n\n", args.old, args.new);
+    md.push_str(&if regressions > 0 {
+        format!("❌ **{} regression(s)**, {} unchanged\n\n", regressions, unchanged)
+    } else {
+        format!("✅ **No regressions**, {} unchanged\n\n", unchanged)
+    });
+    for (change, count) in counts(deltas) {
+        md.push_str(&format!("### {} ({})\n\n| Check | Before | After | Detail |\n| --- | --- | --- | --- |\n", change.label(), count));
+        for delta in deltas.iter().filter(|d| d.change == change) {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                markdown::cell(&name(delta)),
+                markdown::cell(&side(&delta.old_status, delta.old_time_ms, &delta.old_final_url, change)),
+                markdown::cell(&side(&delta.new_status, delta.new_time_ms, &delta.new_final_url, change)),
+                markdown::cell(&delta.detail),
+            ));
+        }
+        md.push('\n');
+    }
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(pinned_to: &str, addr: &str, status: &str) -> ResultRow {
+        ResultRow {
+            url: "https://example.com".to_string(),
+            status: status.to_string(),
+            remote_addr: addr.to_string(),
+            pinned_to: pinned_to.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn args() -> DiffArgs {
+        DiffArgs {
+            old: String::new(),
+            new: String::new(),
+            slower_percent: 50.0,
+            slower_by: Duration::from_millis(100),
+            format: DiffFormat::Table,
+            output: None,
+        }
+    }
+
+    fn diff(old: Vec<ResultRow>, new: Vec<ResultRow>) -> (Vec<Delta>, usize) {
+        compare(&Report { rows: old, final_urls: true }, &Report { rows: new, final_urls: true }, &args())
+    }
+
+    #[test]
+    fn dual_stack_rows_are_compared_per_family() {
+        let (deltas, unchanged) = diff(
+            vec![row("IPv4", "192.0.2.1:443", "200"), row("IPv6", "[2001:db8::1]:443", "200")],
+            vec![row("IPv4", "192.0.2.1:443", "200"), row("IPv6", "", "ERROR")],
+        );
+        assert_eq!(unchanged, 1);
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].change, Change::Broke);
+        assert_eq!(name(&deltas[0]), "https://example.com [IPv6]");
+    }
+
+    #[test]
+    fn each_address_rows_pair_by_address() {
+        // One address was replaced: paired as the only unmatched row of the URL on both sides
+        let (deltas, unchanged) = diff(
+            vec![row("192.0.2.1:443", "192.0.2.1:443", "200"), row("192.0.2.2:443", "192.0.2.2:443", "200")],
+            vec![row("192.0.2.1:443", "192.0.2.1:443", "200"), row("192.0.2.3:443", "192.0.2.3:443", "200")],
+        );
+        assert!(deltas.is_empty());
+        assert_eq!(unchanged, 2);
+
+        // Two replaced addresses can't be told apart
+        let (deltas, _) = diff(
+            vec![row("192.0.2.1:443", "192.0.2.1:443", "200"), row("192.0.2.2:443", "192.0.2.2:443", "200")],
+            vec![row("192.0.2.3:443", "192.0.2.3:443", "200"), row("192.0.2.4:443", "192.0.2.4:443", "200")],
+        );
+        let changes: Vec<Change> = deltas.iter().map(|d| d.change).collect();
+        assert_eq!(changes, [Change::Added, Change::Added, Change::Removed, Change::Removed]);
+    }
+
+    #[test]
+    fn failure_without_connection_is_still_the_same_check() {
+        let (deltas, unchanged) = diff(vec![row("", "192.0.2.1:443", "200")], vec![row("", "", "ERROR")]);
+        assert_eq!(unchanged, 0);
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].change, Change::Broke);
+        assert_eq!(name(&deltas[0]), "https://example.com");
+    }
+
+    #[test]
+    fn appended_reports_keep_the_latest_row_of_every_check() {
+        let dir = std::env::temp_dir();
+        let old = dir.join(format!("url-checker-diff-{}-old.csv", std::process::id()));
+        let new = dir.join(format!("url-checker-diff-{}-new.csv", std::process::id()));
+        let header = "url,status,remote_addr,ip_family,pinned_to";
+        std::fs::write(&old, format!("{}\nhttps://a.example,ERROR,,,\nhttps://b.example,200,192.0.2.5:443,IPv4,\n", header)).unwrap();
+        // Two watch cycles: a recovered on the second one, b went down
+        std::fs::write(
+            &new,
+            format!(
+                "{}\nhttps://a.example,ERROR,,,\nhttps://b.example,200,192.0.2.5:443,IPv4,\n\
+                 https://a.example,200,192.0.2.2:443,IPv4,\nhttps://b.example,ERROR,,,\n",
+                header
+            ),
+        )
+        .unwrap();
+        let (old_report, new_report) = (load(old.to_str().unwrap()), load(new.to_str().unwrap()));
+        std::fs::remove_file(&old).unwrap();
+        std::fs::remove_file(&new).unwrap();
+
+        let new_report = new_report.unwrap();
+        assert_eq!(new_report.rows.len(), 2);
+        let (deltas, unchanged) = compare(&old_report.unwrap(), &new_report, &args());
+        assert_eq!(unchanged, 0);
+        let changes: Vec<(Change, &str)> = deltas.iter().map(|d| (d.change, d.url.as_str())).collect();
+        assert_eq!(changes, [(Change::Broke, "https://b.example"), (Change::Recovered, "https://a.example")]);
+    }
+}
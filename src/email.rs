@@ -0,0 +1,421 @@
+// Email delivery over SMTP: run summaries after a run and immediate mail on state changes
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::alert::{self, Alert};
+use crate::{ResultRow, Stats};
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Security {
+    Starttls, // Plain connection upgraded with STARTTLS (port 587)
+    Tls,      // Implicit TLS from the start (port 465)
+    None,     // Unencrypted, e.g. for a local relay or test server (port 25)
+}
+
+/// When the run summary is mailed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MailOn {
+    Always,  // After every run
+    Failure, // Only when at least one check failed
+    Never,   // Never; only watch-mode alerts are mailed
+}
+
+/// A recipient, given as `[TAG=]ADDRESS` on the command line
+/// Tagged recipients only hear about checks carrying their tag
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    pub tag: Option<String>, // Tag the recipient is responsible for, None = everything
+    pub mailbox: Mailbox,    // Address, optionally with a display name
+}
+
+impl FromStr for Recipient {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Addresses never contain '=' before the '@', so a leading "tag=" is unambiguous
+        let (tag, address) = match s.split_once('=') {
+            Some((tag, address)) if !tag.contains('@') && !tag.contains('<') => (Some(tag.to_string()), address),
+            _ => (None, s),
+        };
+        let mailbox = address
+            .parse()
+            .map_err(|e| anyhow!("invalid email address '{}': {}", address, e))?;
+        Ok(Recipient { tag, mailbox })
+    }
+}
+
+/// SMTP server connection settings
+pub struct SmtpSettings<'a> {
+    pub server: &'a str,                          // SMTP host name
+    pub port: Option<u16>,                        // Port, the security mode's default if None
+    pub security: Security,                       // Connection security
+    pub credentials: Option<(String, String)>,    // User name and password for AUTH
+}
+
+/// Sends report and alert emails
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    recipients: Vec<Recipient>,
+}
+
+impl Mailer {
+    /// Creates a mailer; no connection is made until the first email is sent
+    ///
+    /// # Arguments
+    /// * `smtp` - SMTP server, port, security and credentials
+    /// * `from` - Sender address
+    /// * `recipients` - Recipients, optionally scoped to a tag
+    ///
+    /// # Returns
+    /// * `Result<Mailer>` - Mailer or an error for an invalid server or sender
+    pub fn new(smtp: SmtpSettings, from: &str, recipients: Vec<Recipient>) -> Result<Self> {
+        let builder = match smtp.security {
+            Security::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp.server)?,
+            Security::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(smtp.server)?,
+            Security::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(smtp.server).port(25),
+        };
+        let mut builder = builder.timeout(Some(Duration::from_secs(30)));
+        if let Some(port) = smtp.port {
+            builder = builder.port(port);
+        }
+        if let Some((user, password)) = smtp.credentials {
+            builder = builder.credentials(Credentials::new(user, password));
+        }
+        let from = from
+            .parse()
+            .map_err(|e| anyhow!("invalid sender address '{}': {}", from, e))?;
+        Ok(Mailer { transport: builder.build(), from, recipients })
+    }
+
+    /// Recipients grouped by the tag they are responsible for (None = all checks)
+    fn groups(&self) -> BTreeMap<Option<&str>, Vec<Mailbox>> {
+        let mut groups: BTreeMap<Option<&str>, Vec<Mailbox>> = BTreeMap::new();
+        for recipient in &self.recipients {
+            groups.entry(recipient.tag.as_deref()).or_default().push(recipient.mailbox.clone());
+        }
+        groups
+    }
+
+    /// Mails the run summary; tagged recipients get a summary of their checks only
+    /// A delivery error for one group of recipients does not keep the others from being mailed
+    ///
+    /// # Arguments
+    /// * `rows` - All result rows of the run
+    /// * `output` - Path of the saved report, mentioned in the summary
+    /// * `mail_on` - Whether to mail always or only when a check failed
+    /// * `apdex_threshold` - Satisfied response time for the Apdex score
+    ///
+    /// # Returns
+    /// * `(usize, Vec<String>)` - Number of emails sent, and a message for every email that could not be sent
+    pub async fn send_summary(
+        &self,
+        rows: &[ResultRow],
+        output: &str,
+        mail_on: MailOn,
+        apdex_threshold: Duration,
+    ) -> (usize, Vec<String>) {
+        let mut sent = 0;
+        let mut errors = Vec::new();
+        for (tag, mailboxes) in self.groups() {
+            let rows: Vec<&ResultRow> = rows.iter().filter(|r| tag.map_or(true, |t| r.has_tag(t))).collect();
+            let stats = Stats::from_rows(rows.iter().copied());
+            if rows.is_empty() || mail_on == MailOn::Never || (mail_on == MailOn::Failure && stats.down == 0) {
+                continue;
+            }
+            let scope = tag.map(|t| format!("{}: ", t)).unwrap_or_default();
+            let subject = if stats.down == 0 {
+                format!("[url-checker] {}All {} check(s) passed", scope, stats.total - stats.skipped)
+            } else {
+                format!("[url-checker] {}{} of {} check(s) failed", scope, stats.down, stats.total)
+            };
+            let (text, html) = render_summary(&stats, &rows, output, apdex_threshold);
+            match self.send(&mailboxes, &subject, text, html).await {
+                Ok(()) => sent += 1,
+                Err(e) => errors.push(format!("email to {} failed: {:#}", tag.unwrap_or("all recipients"), e)),
+            }
+        }
+        (sent, errors)
+    }
+
+    /// Mails state changes to every recipient responsible for the affected checks
+    ///
+    /// # Arguments
+    /// * `alerts` - Down and recovered alerts of one cycle
+    ///
+    /// # Returns
+    /// * `Vec<String>` - A message for every email that could not be sent
+    pub async fn send_alerts(&self, alerts: &[Alert]) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (tag, mailboxes) in self.groups() {
+            let alerts: Vec<&Alert> = alerts.iter().filter(|a| tag.map_or(true, |t| a.result.has_tag(t))).collect();
+            let Some(first) = alerts.first() else {
+                continue;
+            };
+            let subject = if alerts.len() == 1 {
+                format!("[url-checker] {}", alert::summary(first))
+            } else {
+                format!("[url-checker] {} state change(s)", alerts.len())
+            };
+            let text = alerts.iter().map(|a| alert::summary(a)).collect::<Vec<_>>().join("\n");
+            let html = format!(
+                "<ul>{}</ul>",
+                alerts.iter().map(|a| format!("<li>{}</li>", escape(&alert::summary(a)))).collect::<String>()
+            );
+            if let Err(e) = self.send(&mailboxes, &subject, text, html).await {
+                errors.push(format!("email to {} failed: {:#}", tag.unwrap_or("all recipients"), e));
+            }
+        }
+        errors
+    }
+
+    /// Sends one multipart (text + HTML) email
+    async fn send(&self, to: &[Mailbox], subject: &str, text: String, html: String) -> Result<()> {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for mailbox in to {
+            message = message.to(mailbox.clone());
+        }
+        let message = message
+            .multipart(MultiPart::alternative_plain_html(text, html))
+            .context("Could not build email")?;
+        self.transport.send(message).await.context("SMTP delivery failed")?;
+        Ok(())
+    }
+}
+
+/// Renders the statistics summary and the failing rows as plain text and HTML
+///
+/// # Arguments
+/// * `stats` - Statistics of the rows
+/// * `rows` - Rows the statistics were computed from
+/// * `output` - Path of the saved report
+/// * `apdex_threshold` - Satisfied response time for the Apdex score
+///
+/// # Returns
+/// * `(String, String)` - Plain-text and HTML bodies
+fn render_summary(stats: &Stats, rows: &[&ResultRow], output: &str, apdex_threshold: Duration) -> (String, String) {
+    let mut summary = stats.summary(apdex_threshold);
+    summary.push(("Report saved to", output.to_string()));
+
+    // Skipped checks are not failures, and a failed scenario is listed once, by its summary row
+    let failing: Vec<&&ResultRow> = rows.iter().filter(|r| r.step.is_empty() && r.is_failure()).collect();
+    let why = |r: &ResultRow| if r.assertions.is_empty() { r.reason.clone() } else { r.assertions.clone() };
+
+    // Plain text mirrors the terminal statistics block
+    let mut text = String::from("STATISTICS\n");
+    for (label, value) in &summary {
+        text.push_str(&format!("  • {:<22} {}\n", format!("{}:", label), value));
+    }
+    if !failing.is_empty() {
+        text.push_str("\nFAILING CHECKS\n");
+        for row in &failing {
+            text.push_str(&format!("  • {} [{}] {}\n", row.url, row.status, why(row)));
+        }
+    }
+
+    let mut html = String::from(
+        "<html><body style=\"font-family: sans-serif\"><h2>URL Checker Summary</h2>\
+         <table cellpadding=\"4\" style=\"border-collapse: collapse\">",
+    );
+    for (label, value) in &summary {
+        html.push_str(&format!("<tr><td><b>{}</b></td><td>{}</td></tr>", escape(label), escape(value)));
+    }
+    html.push_str("</table>");
+    if !failing.is_empty() {
+        html.push_str(
+            "<h3>Failing checks</h3><table border=\"1\" cellpadding=\"4\" style=\"border-collapse: collapse\">\
+             <tr><th>URL</th><th>Status</th><th>Reason</th><th>Checked</th></tr>",
+        );
+        for row in &failing {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td style=\"color: #d93f0b\">{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&row.url),
+                escape(&row.status),
+                escape(&why(row)),
+                escape(&row.timestamp)
+            ));
+        }
+        html.push_str("</table>");
+    }
+    html.push_str("</body></html>");
+
+    (text, html)
+}
+
+/// Escapes text for inclusion in HTML
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// A message received by the stub server: envelope recipients and the DATA section
+    #[derive(Debug)]
+    struct Received {
+        rcpt: Vec<String>,
+        data: String,
+    }
+
+    /// Starts a stub SMTP server accepting every message, except for recipients named "reject"
+    async fn stub() -> (u16, Arc<Mutex<Vec<Received>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let inbox = received.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let inbox = inbox.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    let _ = write.write_all(b"220 stub ESMTP\r\n").await;
+                    let mut rcpt = Vec::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let command = line.to_ascii_uppercase();
+                        let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                            b"250 stub\r\n"
+                        } else if command.starts_with("RCPT TO:") && command.contains("REJECT") {
+                            b"550 No such user\r\n"
+                        } else if command.starts_with("RCPT TO:") {
+                            rcpt.push(line[8..].trim().to_string());
+                            b"250 OK\r\n"
+                        } else if command == "DATA" {
+                            let _ = write.write_all(b"354 Go ahead\r\n").await;
+                            let mut data = String::new();
+                            while let Ok(Some(line)) = lines.next_line().await {
+                                if line == "." {
+                                    break;
+                                }
+                                data.push_str(&line);
+                                data.push('\n');
+                            }
+                            inbox.lock().unwrap().push(Received { rcpt: std::mem::take(&mut rcpt), data });
+                            b"250 Queued\r\n"
+                        } else if command == "QUIT" {
+                            let _ = write.write_all(b"221 Bye\r\n").await;
+                            break;
+                        } else {
+                            b"250 OK\r\n"
+                        };
+                        let _ = write.write_all(reply).await;
+                    }
+                });
+            }
+        });
+        (port, received)
+    }
+
+    fn mailer(port: u16, recipients: &[&str]) -> Mailer {
+        let smtp = SmtpSettings { server: "127.0.0.1", port: Some(port), security: Security::None, credentials: None };
+        let recipients = recipients.iter().map(|r| r.parse().unwrap()).collect();
+        Mailer::new(smtp, "URL Checker <checker@example.com>", recipients).unwrap()
+    }
+
+    fn row(url: &str, status: &str, tags: &str) -> ResultRow {
+        ResultRow {
+            url: url.to_string(),
+            status: status.to_string(),
+            reason: if status == "ERROR" { "connection refused".to_string() } else { "OK".to_string() },
+            tags: tags.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recipients() {
+        let tagged: Recipient = "shop=Ops <ops@example.com>".parse().unwrap();
+        assert_eq!(tagged.tag.as_deref(), Some("shop"));
+        assert_eq!(tagged.mailbox.email.to_string(), "ops@example.com");
+
+        let plain: Recipient = "Ops <ops@example.com>".parse().unwrap();
+        assert_eq!(plain.tag, None);
+        assert!("shop=not an address".parse::<Recipient>().is_err());
+    }
+
+    #[test]
+    fn summary_lists_failing_checks_and_escapes_html() {
+        let rows = [
+            row("https://example.com/?a=<b>", "ERROR", ""),
+            row("https://example.org", "200", ""),
+            row("https://example.net", "SKIPPED", ""),
+            ResultRow { scenario: "shop".to_string(), step: "open".to_string(), ..row("https://shop.example.com", "404", "") },
+            ResultRow { scenario: "shop".to_string(), reason: "open failed: 404".to_string(), ..row("scenario:shop", "FAIL", "") },
+        ];
+        let rows: Vec<&ResultRow> = rows.iter().collect();
+        let stats = Stats::from_rows(rows.iter().copied());
+        let (text, html) = render_summary(&stats, &rows, "report.csv", Duration::from_millis(500));
+
+        assert!(text.contains(
+            "FAILING CHECKS\n  • https://example.com/?a=<b> [ERROR] connection refused\n  • scenario:shop [FAIL] open failed: 404\n"
+        ));
+        assert!(!text.contains("https://example.org"));
+        assert!(!text.contains("https://example.net"), "skipped checks are not failures");
+        assert!(!text.contains("https://shop.example.com"), "failed steps are covered by the scenario row");
+        assert!(text.contains("report.csv"));
+        assert!(html.contains("<td>https://example.com/?a=&lt;b&gt;</td>"));
+        assert!(!html.contains("https://example.org"));
+    }
+
+    #[tokio::test]
+    async fn summaries_go_to_the_recipients_of_each_tag() {
+        let (port, received) = stub().await;
+        let mailer = mailer(port, &["all@example.com", "shop=shop@example.com", "blog=blog@example.com"]);
+        let rows = [row("https://shop.example.com", "ERROR", "shop"), row("https://blog.example.com", "200", "blog")];
+
+        // Only the recipients who see a failure are mailed
+        let (sent, errors) = mailer.send_summary(&rows, "report.csv", MailOn::Failure, Duration::from_millis(500)).await;
+        assert_eq!((sent, errors.len()), (2, 0));
+
+        let received = received.lock().unwrap();
+        let mut to: Vec<&str> = received.iter().map(|m| m.rcpt[0].as_str()).collect();
+        to.sort();
+        assert_eq!(to, ["<all@example.com>", "<shop@example.com>"]);
+
+        let shop = received.iter().find(|m| m.rcpt == ["<shop@example.com>"]).unwrap();
+        assert!(shop.data.contains("Subject: [url-checker] shop: 1 of 1 check(s) failed\n"));
+        assert!(shop.data.contains("To: shop@example.com\n"));
+        assert!(shop.data.contains("From: \"URL Checker\" <checker@example.com>\n"));
+        assert!(shop.data.contains("https://shop.example.com"));
+        assert!(!shop.data.contains("https://blog.example.com"));
+
+        let all = received.iter().find(|m| m.rcpt == ["<all@example.com>"]).unwrap();
+        assert!(all.data.contains("Subject: [url-checker] 1 of 2 check(s) failed\n"));
+    }
+
+    #[tokio::test]
+    async fn delivery_errors_are_reported() {
+        // The shop group is rejected; the others are still mailed
+        let (port, received) = stub().await;
+        let partly = mailer(port, &["all@example.com", "shop=reject@example.com", "blog=blog@example.com"]);
+        let rows = [row("https://shop.example.com", "ERROR", "shop"), row("https://blog.example.com", "ERROR", "blog")];
+        let (sent, errors) = partly.send_summary(&rows, "report.csv", MailOn::Always, Duration::from_millis(500)).await;
+        assert_eq!(sent, 2);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("email to shop failed: SMTP delivery failed"), "{}", errors[0]);
+        assert_eq!(received.lock().unwrap().len(), 2);
+
+        // Nothing listens on a port that was just released
+        let port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let unreachable = mailer(port, &["ops@example.com"]);
+        let (sent, errors) = unreachable.send_summary(&rows, "report.csv", MailOn::Always, Duration::from_millis(500)).await;
+        assert_eq!(sent, 0);
+        assert!(errors[0].starts_with("email to all recipients failed: SMTP delivery failed"));
+    }
+}
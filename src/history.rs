@@ -0,0 +1,601 @@
+// Persistent check history in SQLite: runs, their result rows, and retention
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use clap::Subcommand;
+use colored::*;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{classify, timeouts, Args, ResultRow, Stats};
+
+/// Subcommands of `history`
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// List recorded runs, newest first
+    Runs {
+        /// Maximum number of runs to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show the recorded results of one URL (or "scenario:<name>"), newest first
+    Url {
+        /// URL as given in the input
+        url: String,
+
+        /// Maximum number of results to show
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Delete old results, and runs together with their results
+    Prune {
+        /// Delete results checked longer ago than this (e.g. 30d, 12h), and runs left without results
+        #[arg(long, value_parser = timeouts::parse_duration)]
+        older_than: Option<Duration>,
+
+        /// Keep only this many of the most recent runs
+        #[arg(long)]
+        keep_runs: Option<usize>,
+    },
+}
+
+/// A recorded run
+pub struct RunInfo {
+    pub id: i64,                        // Run id
+    pub started_at: DateTime<Utc>,      // When the run started
+    pub finished_at: Option<DateTime<Utc>>, // When the run (or the latest watch cycle) finished
+    pub mode: String,                   // "run" or "watch"
+    pub input: String,                  // Input file
+    pub total: i64,                     // Number of rows recorded
+    pub up: i64,                        // Successful rows
+    pub down: i64,                      // Failed rows
+    pub skipped: i64,                   // Rows skipped by the deadline
+}
+
+/// A result row as stored, with the run it belongs to
+pub struct StoredResult {
+    pub run_id: i64,                    // Run the row was recorded in
+    pub checked_at: DateTime<Utc>,      // When the check was performed
+    pub row: ResultRow,                 // The row itself
+}
+
+/// SQLite-backed store of runs and result rows
+pub struct History {
+    conn: Connection,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at  INTEGER NOT NULL,
+    finished_at INTEGER,
+    mode        TEXT NOT NULL,
+    input       TEXT NOT NULL,
+    output      TEXT NOT NULL,
+    format      TEXT NOT NULL,
+    version     TEXT NOT NULL,
+    total       INTEGER NOT NULL DEFAULT 0,
+    up          INTEGER NOT NULL DEFAULT 0,
+    down        INTEGER NOT NULL DEFAULT 0,
+    skipped     INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS results (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id       INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    checked_at   INTEGER NOT NULL,
+    up           INTEGER NOT NULL,
+    url          TEXT NOT NULL,
+    status       TEXT NOT NULL,
+    reason       TEXT NOT NULL,
+    time_ms      INTEGER NOT NULL,
+    size_bytes   INTEGER NOT NULL,
+    timestamp    TEXT NOT NULL,
+    proxy        TEXT NOT NULL,
+    remote_addr  TEXT NOT NULL,
+    ip_family    TEXT NOT NULL,
+    pinned_to    TEXT NOT NULL DEFAULT '',
+    http_version TEXT NOT NULL,
+    alpn         TEXT NOT NULL,
+    cert_expires TEXT NOT NULL DEFAULT '',
+    final_url    TEXT NOT NULL DEFAULT '',
+    scenario     TEXT NOT NULL,
+    step         TEXT NOT NULL,
+    assertions   TEXT NOT NULL,
+    tags         TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS results_url ON results (url, checked_at);
+CREATE INDEX IF NOT EXISTS results_run ON results (run_id);
+";
+
+/// Result columns added after the first release, created in older databases on open
+const ADDED_COLUMNS: [&str; 3] = ["cert_expires", "final_url", "pinned_to"];
+
+impl History {
+    /// Opens (and if needed creates) a history database
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path).with_context(|| format!("Could not open history database {}", path))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("Could not create history tables in {}", path))?;
+        migrate(&conn).with_context(|| format!("Could not upgrade history tables in {}", path))?;
+        Ok(History { conn })
+    }
+
+    /// Records the start of a run and returns its id
+    ///
+    /// # Arguments
+    /// * `mode` - "run" for a single run, "watch" for a watch session
+    /// * `input` - Input file
+    /// * `output` - Output file
+    /// * `format` - Output format
+    ///
+    /// # Returns
+    /// * `Result<i64>` - Id of the new run
+    pub fn start_run(&self, mode: &str, input: &str, output: &str, format: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO runs (started_at, mode, input, output, format, version) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![Utc::now().timestamp(), mode, input, output, format, env!("CARGO_PKG_VERSION")],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Stores result rows of a run and adds them to the run's counters
+    ///
+    /// # Arguments
+    /// * `run_id` - Run the rows belong to
+    /// * `rows` - Result rows to store
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok or a database error
+    pub fn record(&mut self, run_id: i64, rows: &[ResultRow]) -> Result<()> {
+        let stats = Stats::from_rows(rows);
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO results (run_id, checked_at, up, url, status, reason, time_ms, size_bytes, timestamp,
+                     proxy, remote_addr, ip_family, pinned_to, http_version, alpn, cert_expires, final_url, scenario,
+                     step, assertions, tags)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+            )?;
+            for row in rows {
+                insert.execute(params![
+                    run_id,
+                    checked_at(&row.timestamp).timestamp(),
+                    row.is_up(),
+                    row.url,
+                    row.status,
+                    row.reason,
+                    row.time_ms as i64,
+                    row.size_bytes as i64,
+                    row.timestamp,
+                    row.proxy,
+                    row.remote_addr,
+                    row.ip_family,
+                    row.pinned_to,
+                    row.http_version,
+                    row.alpn,
+                    row.cert_expires,
+                    row.final_url,
+                    row.scenario,
+                    row.step,
+                    row.assertions,
+                    row.tags,
+                ])?;
+            }
+        }
+        tx.execute(
+            "UPDATE runs SET finished_at = ?2, total = total + ?3, up = up + ?4, down = down + ?5, skipped = skipped + ?6
+             WHERE id = ?1",
+            params![
+                run_id,
+                Utc::now().timestamp(),
+                stats.total as i64,
+                stats.up as i64,
+                stats.down as i64,
+                stats.skipped as i64
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Lists the most recent runs, newest first
+    pub fn runs(&self, limit: usize) -> Result<Vec<RunInfo>> {
+        let mut query = self.conn.prepare(
+            "SELECT id, started_at, finished_at, mode, input, total, up, down, skipped
+             FROM runs ORDER BY id DESC LIMIT ?1",
+        )?;
+        let runs = query
+            .query_map(params![limit as i64], |r| {
+                Ok(RunInfo {
+                    id: r.get(0)?,
+                    started_at: from_epoch(r.get(1)?),
+                    finished_at: r.get::<_, Option<i64>>(2)?.map(from_epoch),
+                    mode: r.get(3)?,
+                    input: r.get(4)?,
+                    total: r.get(5)?,
+                    up: r.get(6)?,
+                    down: r.get(7)?,
+                    skipped: r.get(8)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(runs)
+    }
+
+    /// Returns stored results, oldest first
+    ///
+    /// # Arguments
+    /// * `url` - Only rows of this URL, or all rows if None
+    /// * `since` - Only rows checked at or after this time, or all if None
+    /// * `limit` - Only the most recent rows, or all if None
+    ///
+    /// # Returns
+    /// * `Result<Vec<StoredResult>>` - Matching rows
+    pub fn results(&self, url: Option<&str>, since: Option<DateTime<Utc>>, limit: Option<usize>) -> Result<Vec<StoredResult>> {
+        let mut query = self.conn.prepare(
+            "SELECT run_id, checked_at, url, status, reason, time_ms, size_bytes, timestamp, proxy, remote_addr,
+                    ip_family, pinned_to, http_version, alpn, cert_expires, final_url, scenario, step, assertions, tags
+             FROM results
+             WHERE (?1 IS NULL OR url = ?1) AND checked_at >= ?2
+             ORDER BY checked_at DESC, id DESC LIMIT ?3",
+        )?;
+        let since = since.map(|t| t.timestamp()).unwrap_or(i64::MIN);
+        let limit = limit.map(|l| l as i64).unwrap_or(-1);
+        let mut results = query
+            .query_map(params![url, since, limit], |r| {
+                Ok(StoredResult {
+                    run_id: r.get(0)?,
+                    checked_at: from_epoch(r.get(1)?),
+                    row: ResultRow {
+                        url: r.get(2)?,
+                        status: r.get(3)?,
+                        reason: r.get(4)?,
+                        time_ms: r.get::<_, i64>(5)? as u128,
+                        size_bytes: r.get::<_, i64>(6)? as u64,
+                        timestamp: r.get(7)?,
+                        proxy: r.get(8)?,
+                        remote_addr: r.get(9)?,
+                        ip_family: r.get(10)?,
+                        pinned_to: r.get(11)?,
+                        http_version: r.get(12)?,
+                        alpn: r.get(13)?,
+                        cert_expires: r.get(14)?,
+                        final_url: r.get(15)?,
+                        scenario: r.get(16)?,
+                        step: r.get(17)?,
+                        assertions: r.get(18)?,
+                        tags: r.get(19)?,
+                        ..Default::default()
+                    },
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        results.reverse();
+        Ok(results)
+    }
+
+    /// Deletes old results and runs
+    /// Results age by `checked_at`, so a watch session longer than the retention sheds its
+    /// old results but keeps its run; runs only go once no results are left
+    ///
+    /// # Arguments
+    /// * `older_than` - Delete results checked before now minus this age, then runs that
+    ///   started before it and have no results left
+    /// * `keep_runs` - Keep only this many of the most recent runs
+    /// * `recording` - Run that is still recording, which is never deleted
+    ///
+    /// # Returns
+    /// * `Result<(usize, usize)>` - Number of deleted runs and result rows
+    pub fn prune(
+        &mut self,
+        older_than: Option<Duration>,
+        keep_runs: Option<usize>,
+        recording: Option<i64>,
+    ) -> Result<(usize, usize)> {
+        // Run ids start at 1, so 0 protects nothing
+        let recording = recording.unwrap_or(0);
+        let tx = self.conn.transaction()?;
+        let rows_before: i64 = tx.query_row("SELECT COUNT(*) FROM results", [], |r| r.get(0))?;
+        let mut runs = 0;
+        if let Some(age) = older_than {
+            let cutoff = (Utc::now() - chrono::Duration::from_std(age)?).timestamp();
+            tx.execute("DELETE FROM results WHERE checked_at < ?1", params![cutoff])?;
+            runs += tx.execute(
+                "DELETE FROM runs WHERE started_at < ?1 AND id != ?2
+                 AND NOT EXISTS (SELECT 1 FROM results WHERE results.run_id = runs.id)",
+                params![cutoff, recording],
+            )?;
+        }
+        if let Some(keep) = keep_runs {
+            let oldest_kept: Option<i64> = tx
+                .query_row(
+                    "SELECT id FROM runs ORDER BY id DESC LIMIT 1 OFFSET ?1",
+                    params![keep.saturating_sub(1) as i64],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            runs += match (keep, oldest_kept) {
+                (0, _) => tx.execute("DELETE FROM runs WHERE id != ?1", params![recording])?,
+                (_, Some(id)) => tx.execute("DELETE FROM runs WHERE id < ?1 AND id != ?2", params![id, recording])?,
+                (_, None) => 0,
+            };
+        }
+        let rows_after: i64 = tx.query_row("SELECT COUNT(*) FROM results", [], |r| r.get(0))?;
+        tx.commit()?;
+        Ok((runs, (rows_before - rows_after) as usize))
+    }
+}
+
+/// Records the rows of one run (or watch session) and applies the retention policy
+pub struct Recorder {
+    history: History,
+    run_id: i64,
+    retention: Option<Duration>,
+}
+
+impl Recorder {
+    /// Opens the history database and records the start of a run, if `--history` is set
+    ///
+    /// # Arguments
+    /// * `args` - Command-line arguments with the history settings
+    /// * `mode` - "run" for a single run, "watch" for a watch session
+    ///
+    /// # Returns
+    /// * `Result<Option<Recorder>>` - Recorder, None without `--history`, or a database error
+    pub fn start(args: &Args, mode: &str) -> Result<Option<Self>> {
+        let Some(path) = &args.history else {
+            return Ok(None);
+        };
+        let history = History::open(path)?;
+        let reports = args.reports()?;
+        let paths = reports.iter().map(|r| r.path.as_str()).collect::<Vec<_>>().join(",");
+        let formats = reports.iter().map(|r| r.format.as_str()).collect::<Vec<_>>().join(",");
+        let run_id = history.start_run(mode, &args.input, &paths, &formats)?;
+        Ok(Some(Recorder { history, run_id, retention: args.history_retention }))
+    }
+
+    /// Stores rows and deletes results and runs that fell out of the retention window
+    pub fn record(&mut self, rows: &[ResultRow]) -> Result<()> {
+        self.history.record(self.run_id, rows).context("Could not record results in the history")?;
+        if self.retention.is_some() {
+            self.history.prune(self.retention, None, Some(self.run_id))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a row timestamp ("2024-01-31 12:00:00 UTC"), falling back to now
+fn checked_at(timestamp: &str) -> DateTime<Utc> {
+    NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S UTC")
+        .map(|t| Utc.from_utc_datetime(&t))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+fn from_epoch(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(secs, 0).single().unwrap_or_default()
+}
+
+/// Adds the result columns a database created by an older version lacks
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let existing = conn
+        .prepare("SELECT name FROM pragma_table_info('results')")?
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for column in ADDED_COLUMNS.iter().filter(|c| !existing.iter().any(|e| e == *c)) {
+        conn.execute_batch(&format!("ALTER TABLE results ADD COLUMN {} TEXT NOT NULL DEFAULT ''", column))?;
+    }
+    Ok(())
+}
+
+/// Runs a `history` subcommand
+///
+/// # Arguments
+/// * `path` - History database
+/// * `command` - Subcommand to run
+///
+/// # Returns
+/// * `Result<()>` - Ok or a database error
+pub fn run_command(path: &str, command: &HistoryCommand) -> Result<()> {
+    let mut history = History::open(path)?;
+    match command {
+        HistoryCommand::Runs { limit } => {
+            let runs = history.runs(*limit)?;
+            println!("{:<6} {:<20} {:<10} {:<6} {:<8} {:<8} {:<8} {}",
+                "RUN".bold(),
+                "STARTED (UTC)".bold(),
+                "DURATION".bold(),
+                "MODE".bold(),
+                "TOTAL".bold(),
+                "UP".bold(),
+                "DOWN".bold(),
+                "INPUT".bold()
+            );
+            println!("{}", "─".repeat(100).bright_black());
+            for run in runs {
+                let duration = run
+                    .finished_at
+                    .map(|f| timeouts::format_duration(Duration::from_secs((f - run.started_at).num_seconds().max(0) as u64)))
+                    .unwrap_or_else(|| "-".to_string());
+                println!("{:<6} {:<20} {:<10} {:<6} {:<8} {:<8} {:<8} {}",
+                    run.id,
+                    run.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    duration,
+                    run.mode,
+                    run.total,
+                    run.up.to_string().green(),
+                    if run.down > 0 { run.down.to_string().red() } else { run.down.to_string().normal() },
+                    run.input
+                );
+                if run.skipped > 0 {
+                    println!("{:<6} {}", "", format!("{} skipped", run.skipped).yellow());
+                }
+            }
+        }
+        HistoryCommand::Url { url, limit } => {
+            let results = history.results(Some(url), None, Some(*limit))?;
+            if results.is_empty() {
+                println!("{} No history for {}", "ℹ".cyan(), url);
+                return Ok(());
+            }
+            println!("{:<6} {:<24} {:<8} {:<10} {}",
+                "RUN".bold(),
+                "CHECKED".bold(),
+                "STATUS".bold(),
+                "TIME (ms)".bold(),
+                "RESULT".bold()
+            );
+            println!("{}", "─".repeat(100).bright_black());
+            for result in results.iter().rev() {
+                let (status_color, status_icon, result_text) = classify(&result.row);
+                println!("{:<6} {:<24} {:<8} {:<10} {} {} {}",
+                    result.run_id,
+                    result.checked_at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                    status_color,
+                    result.row.time_ms,
+                    status_icon,
+                    result_text,
+                    result.row.reason.bright_black()
+                );
+            }
+        }
+        HistoryCommand::Prune { older_than, keep_runs } => {
+            if older_than.is_none() && keep_runs.is_none() {
+                anyhow::bail!("Nothing to prune: pass --older-than and/or --keep-runs");
+            }
+            let (runs, rows) = history.prune(*older_than, *keep_runs, None)?;
+            println!("{} Deleted {} run(s) with {} result(s)", "✓".green(), runs, rows);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn row(url: &str, age: Duration) -> ResultRow {
+        let at = Utc::now() - chrono::Duration::from_std(age).unwrap();
+        ResultRow {
+            url: url.to_string(),
+            status: "200".to_string(),
+            timestamp: at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Starts a run that began `age` ago
+    fn start(history: &History, mode: &str, age: Duration) -> i64 {
+        let id = history.start_run(mode, "urls.txt", "report.csv", "csv").unwrap();
+        let started = (Utc::now() - chrono::Duration::from_std(age).unwrap()).timestamp();
+        history.conn.execute("UPDATE runs SET started_at = ?1 WHERE id = ?2", params![started, id]).unwrap();
+        id
+    }
+
+    fn run_ids(history: &History) -> Vec<i64> {
+        history.runs(100).unwrap().iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        let mut history = History::open(":memory:").unwrap();
+        let run = start(&history, "run", Duration::ZERO);
+        let recorded = ResultRow {
+            status: "200".to_string(),
+            reason: "OK".to_string(),
+            time_ms: 120,
+            size_bytes: 5,
+            proxy: "http://proxy:8080".to_string(),
+            remote_addr: "192.0.2.1:443".to_string(),
+            ip_family: "IPv4".to_string(),
+            pinned_to: "IPv4".to_string(),
+            http_version: "HTTP/2".to_string(),
+            alpn: "h2".to_string(),
+            cert_expires: "2027-01-01 00:00:00 UTC".to_string(),
+            final_url: "https://a.example/home".to_string(),
+            scenario: "shop".to_string(),
+            step: "open".to_string(),
+            assertions: "expected body to contain 'ok'".to_string(),
+            tags: "prod,web".to_string(),
+            ..row("https://a.example/", Duration::ZERO)
+        };
+        history.record(run, std::slice::from_ref(&recorded)).unwrap();
+
+        let stored = history.results(None, None, None).unwrap().remove(0).row;
+        let json = |row: &ResultRow| serde_json::to_value(row).unwrap();
+        assert_eq!(json(&stored), json(&recorded));
+    }
+
+    #[test]
+    fn older_databases_get_the_added_columns() {
+        let path = std::env::temp_dir().join(format!("url-checker-history-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        {
+            let conn = Connection::open(path).unwrap();
+            let mut old_schema = SCHEMA.to_string();
+            for column in ADDED_COLUMNS {
+                old_schema = old_schema.replace(&format!("    {:<12} TEXT NOT NULL DEFAULT '',\n", column), "");
+            }
+            assert!(ADDED_COLUMNS.iter().all(|c| !old_schema.contains(c)));
+            conn.execute_batch(&old_schema).unwrap();
+        }
+        let mut history = History::open(path).unwrap();
+        let run = start(&history, "run", Duration::ZERO);
+        let recorded = ResultRow { final_url: "https://a.example/home".to_string(), ..row("https://a.example/", Duration::ZERO) };
+        history.record(run, &[recorded]).unwrap();
+        let stored = history.results(None, None, None).unwrap();
+        drop(history);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+        assert_eq!(stored[0].row.final_url, "https://a.example/home");
+    }
+
+    #[test]
+    fn retention_prunes_results_not_the_recording_run() {
+        let mut history = History::open(":memory:").unwrap();
+        let old = start(&history, "run", 3 * DAY);
+        history.record(old, &[row("https://a.example/", 3 * DAY)]).unwrap();
+        let watch = start(&history, "watch", 2 * DAY);
+        history.record(watch, &[row("https://a.example/", 2 * DAY), row("https://a.example/", Duration::ZERO)]).unwrap();
+
+        assert_eq!(history.prune(Some(DAY), None, Some(watch)).unwrap(), (1, 2));
+        assert_eq!(run_ids(&history), [watch]);
+
+        // The session keeps recording into its run
+        history.record(watch, &[row("https://a.example/", Duration::ZERO)]).unwrap();
+        let results = history.results(None, None, None).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.run_id == watch));
+    }
+
+    #[test]
+    fn recording_run_survives_without_results() {
+        let mut history = History::open(":memory:").unwrap();
+        let watch = start(&history, "watch", 2 * DAY);
+        history.record(watch, &[row("https://a.example/", 2 * DAY)]).unwrap();
+
+        assert_eq!(history.prune(Some(DAY), None, Some(watch)).unwrap(), (0, 1));
+        history.record(watch, &[row("https://a.example/", Duration::ZERO)]).unwrap();
+
+        // Once it is no longer recording, an empty old run goes
+        history.conn.execute("DELETE FROM results", []).unwrap();
+        assert_eq!(history.prune(Some(DAY), None, None).unwrap(), (1, 0));
+        assert!(run_ids(&history).is_empty());
+    }
+
+    #[test]
+    fn keep_runs_spares_the_recording_run() {
+        let mut history = History::open(":memory:").unwrap();
+        let watch = start(&history, "watch", DAY);
+        let newer = start(&history, "run", Duration::ZERO);
+        history.record(watch, &[row("https://a.example/", Duration::ZERO)]).unwrap();
+
+        assert_eq!(history.prune(None, Some(1), Some(watch)).unwrap(), (0, 0));
+        assert_eq!(run_ids(&history), [newer, watch]);
+        assert_eq!(history.prune(None, Some(0), Some(watch)).unwrap(), (1, 0));
+        assert_eq!(run_ids(&history), [watch]);
+    }
+}
@@ -0,0 +1,131 @@
+// User-defined hook commands run on failed checks and on run completion
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use futures::stream::{self, StreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::ResultRow;
+
+/// Runs a hook command through the system shell
+/// The payload is written to the command's stdin as JSON; its stdout goes to stderr so it
+/// cannot end up in a report written to stdout, its stderr is passed through
+/// The timeout covers writing the payload too, so a hook that never reads stdin cannot
+/// block the run once the pipe buffer is full
+///
+/// # Arguments
+/// * `command` - Shell command line
+/// * `env` - Extra environment variables
+/// * `payload` - JSON document written to stdin
+/// * `timeout` - Time after which the command is killed
+///
+/// # Returns
+/// * `Result<()>` - Ok if the command exited successfully, an error otherwise
+pub async fn run(command: &str, env: &[(String, String)], payload: &serde_json::Value, timeout: Duration) -> Result<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::piped())
+        .stdout(std::io::stderr())
+        .kill_on_drop(true);
+
+    let payload = serde_json::to_string_pretty(payload)?;
+    let mut child = cmd.spawn().with_context(|| format!("Could not start hook '{}'", command))?;
+    let stdin = child.stdin.take();
+    let exchange = async {
+        if let Some(mut stdin) = stdin {
+            // A hook that does not read its stdin closes the pipe early; that is not an error
+            let _ = stdin.write_all(payload.as_bytes()).await;
+            // Dropping stdin closes it, so hooks reading to the end can finish
+        }
+        child.wait().await
+    };
+
+    let status = match tokio::time::timeout(timeout, exchange).await {
+        Ok(status) => status?,
+        Err(_) => {
+            let _ = child.kill().await;
+            bail!("hook '{}' killed after {}", command, crate::timeouts::format_duration(timeout));
+        }
+    };
+    if !status.success() {
+        bail!("hook '{}' exited with {}", command, status);
+    }
+    Ok(())
+}
+
+/// Environment variables describing a single result row
+pub fn row_env(row: &ResultRow) -> Vec<(String, String)> {
+    vec![
+        ("URL_CHECKER_URL".to_string(), row.url.clone()),
+        ("URL_CHECKER_STATUS".to_string(), row.status.clone()),
+        ("URL_CHECKER_REASON".to_string(), row.reason.clone()),
+        ("URL_CHECKER_TIME_MS".to_string(), row.time_ms.to_string()),
+        ("URL_CHECKER_TIMESTAMP".to_string(), row.timestamp.clone()),
+        ("URL_CHECKER_REMOTE_ADDR".to_string(), row.remote_addr.clone()),
+        ("URL_CHECKER_SCENARIO".to_string(), row.scenario.clone()),
+        ("URL_CHECKER_STEP".to_string(), row.step.clone()),
+        ("URL_CHECKER_ASSERTIONS".to_string(), row.assertions.clone()),
+        ("URL_CHECKER_TAGS".to_string(), row.tags.clone()),
+    ]
+}
+
+/// Runs the failure hook once per failed row, up to `concurrency` at a time
+///
+/// # Arguments
+/// * `command` - Shell command line
+/// * `rows` - Failed rows, each passed as JSON on stdin and as environment variables
+/// * `concurrency` - Maximum number of hooks running at once
+/// * `timeout` - Time after which a hook is killed
+///
+/// # Returns
+/// * `Vec<String>` - A message for every hook that failed
+pub async fn on_failure(command: &str, rows: &[&ResultRow], concurrency: usize, timeout: Duration) -> Vec<String> {
+    stream::iter(rows.iter().map(|row| async move {
+        let payload = serde_json::to_value(row).unwrap_or_default();
+        run(command, &row_env(row), &payload, timeout)
+            .await
+            .err()
+            .map(|e| format!("{} ({}): {:#}", row.url, row.status, e))
+    }))
+    .buffer_unordered(concurrency)
+    .filter_map(|error| async move { error })
+    .collect()
+    .await
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn timeout_covers_an_unread_payload() {
+        // Far larger than a pipe buffer, for a hook that never reads it
+        let payload = serde_json::json!({ "data": "x".repeat(4 * 1024 * 1024) });
+        let started = Instant::now();
+        let error = run("sleep 30", &[], &payload, Duration::from_millis(300)).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(error.to_string().contains("killed after"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn payload_and_environment_reach_the_hook() {
+        let env = [("URL_CHECKER_URL".to_string(), "https://a.example/".to_string())];
+        let payload = serde_json::json!({ "status": "500" });
+        let check = r#"test "$URL_CHECKER_URL" = https://a.example/ && grep -q '"status": "500"'"#;
+        run(check, &env, &payload, Duration::from_secs(5)).await.unwrap();
+        let error = run("exit 3", &env, &payload, Duration::from_secs(5)).await.unwrap_err();
+        assert!(error.to_string().contains("exited with"), "{}", error);
+    }
+}
@@ -0,0 +1,287 @@
+// Self-contained HTML report: summary, breakdowns, latency chart and a sortable results table
+use serde_json::Value;
+
+use crate::category::{self, Category};
+use crate::email::escape;
+use crate::{format_size, ResultRow};
+
+/// Inline stylesheet; the report must not load anything from the network
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Roboto, sans-serif; margin: 2em; color: #24292f; background: #fff; }
+h1 { margin-bottom: 0; }
+.muted { color: #6e7781; }
+.cards { display: flex; flex-wrap: wrap; gap: 1em; margin: 1.5em 0; }
+.card { border: 1px solid #d0d7de; border-radius: 6px; padding: 0.8em 1.2em; min-width: 9em; }
+.card .value { font-size: 1.6em; font-weight: 600; }
+.card .label { color: #6e7781; font-size: 0.85em; }
+.up { color: #1a7f37; } .down { color: #cf222e; } .skipped { color: #9a6700; }
+.panels { display: flex; flex-wrap: wrap; gap: 2em; }
+.panel { flex: 1; min-width: 20em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.35em 0.6em; border-bottom: 1px solid #d0d7de; vertical-align: top; }
+#results th { cursor: pointer; user-select: none; background: #f6f8fa; position: sticky; top: 0; }
+#results th.asc::after { content: " ▲"; } #results th.desc::after { content: " ▼"; }
+td.num, th.num { text-align: right; }
+.bar { background: #0969da; height: 0.9em; display: inline-block; border-radius: 2px; }
+.bar.down { background: #cf222e; }
+.filters { margin: 1em 0; display: flex; gap: 1em; }
+.filters input { flex: 1; padding: 0.4em; }
+.reason { max-width: 40em; word-break: break-word; }
+"#;
+
+/// Sorting and filtering of the results table
+const SCRIPT: &str = r#"
+const table = document.getElementById('results');
+const body = table.tBodies[0];
+const search = document.getElementById('search');
+const state = document.getElementById('state');
+function applyFilter() {
+  const text = search.value.toLowerCase();
+  for (const row of body.rows) {
+    const matchesText = row.textContent.toLowerCase().includes(text);
+    const matchesState = state.value === 'all' || row.dataset.state === state.value;
+    row.style.display = matchesText && matchesState ? '' : 'none';
+  }
+}
+search.addEventListener('input', applyFilter);
+state.addEventListener('change', applyFilter);
+table.querySelectorAll('th').forEach((th, index) => th.addEventListener('click', () => {
+  const ascending = !th.classList.contains('asc');
+  table.querySelectorAll('th').forEach(h => h.classList.remove('asc', 'desc'));
+  th.classList.add(ascending ? 'asc' : 'desc');
+  const numeric = th.classList.contains('num');
+  const key = row => {
+    const cell = row.cells[index];
+    const value = cell.dataset.sort ?? cell.textContent;
+    return numeric ? parseFloat(value) || 0 : value.toLowerCase();
+  };
+  const rows = Array.from(body.rows).sort((a, b) => {
+    const x = key(a), y = key(b);
+    return (x < y ? -1 : x > y ? 1 : 0) * (ascending ? 1 : -1);
+  });
+  rows.forEach(row => body.appendChild(row));
+}));
+"#;
+
+/// Renders the HTML report
+/// Statistics and breakdowns come from the metadata of the JSON report document, so both
+/// exports always agree; the table lists the result rows
+///
+/// # Arguments
+/// * `document` - JSON report document (metadata and results)
+/// * `rows` - Result rows of the run
+///
+/// # Returns
+/// * `String` - Complete HTML page without external resources
+pub fn render(document: &Value, rows: &[ResultRow]) -> String {
+    let meta = &document["metadata"];
+    let int = |key: &str| meta[key].as_u64().unwrap_or(0);
+    let ms = |key: &str| meta[key].as_u64().map(|v| format!("{} ms", v)).unwrap_or_else(|| "N/A".to_string());
+    let total = int("total_urls");
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str("<title>URL Checker Report</title>\n");
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str("<h1>URL Checker Report</h1>\n");
+    html.push_str(&format!(
+        "<p class=\"muted\">Generated {}</p>\n",
+        escape(meta["generated_at"].as_str().unwrap_or(""))
+    ));
+
+    // Summary cards mirror the terminal statistics
+    let percent = |n: u64| if total > 0 { n as f64 / total as f64 * 100.0 } else { 0.0 };
+    let apdex = meta["apdex"]
+        .as_f64()
+        .map(|score| format!("{:.2}", score))
+        .unwrap_or_else(|| "N/A".to_string());
+    let apdex_label = format!("Apdex (T={} ms)", int("apdex_threshold_ms"));
+    let cards = [
+        ("Total checks", total.to_string(), ""),
+        ("Successful", format!("{} ({:.1}%)", int("successful"), percent(int("successful"))), "up"),
+        ("Failed", format!("{} ({:.1}%)", int("failed"), percent(int("failed"))), "down"),
+        ("Skipped", int("skipped").to_string(), "skipped"),
+        ("Average", ms("avg_time_ms"), ""),
+        ("p50", ms("p50_time_ms"), ""),
+        ("p95", ms("p95_time_ms"), ""),
+        ("p99", ms("p99_time_ms"), ""),
+        ("Slowest", ms("max_time_ms"), ""),
+        (apdex_label.as_str(), apdex, ""),
+        ("Data received", format_size(int("total_size_bytes")), ""),
+    ];
+    html.push_str("<div class=\"cards\">\n");
+    for (label, value, class) in &cards {
+        html.push_str(&format!(
+            "<div class=\"card\"><div class=\"value {}\">{}</div><div class=\"label\">{}</div></div>\n",
+            class,
+            escape(value),
+            escape(label)
+        ));
+    }
+    html.push_str("</div>\n");
+
+    // Breakdowns and latency chart
+    html.push_str("<div class=\"panels\">\n");
+    let statuses: Vec<(String, u64, bool)> = meta["status_codes"]
+        .as_object()
+        .map(|m| {
+            m.iter()
+                .map(|(status, count)| {
+                    let up = status.starts_with('2') || status.starts_with('3') || status == "PASS";
+                    (status.clone(), count.as_u64().unwrap_or(0), up)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    html.push_str(&breakdown("Status codes", &statuses));
+    let categories: Vec<(String, u64, bool)> = meta["error_categories"]
+        .as_object()
+        .map(|m| {
+            m.iter()
+                .map(|(name, count)| {
+                    let label = Category::from_name(name).map(|c| c.label()).unwrap_or(name);
+                    (label.to_string(), count.as_u64().unwrap_or(0), false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    html.push_str(&breakdown("Error categories", &categories));
+    html.push_str(&latency_chart(&meta["latency_histogram"]));
+    html.push_str("</div>\n");
+
+    if let Some(mismatches) = meta["dual_stack_mismatches"].as_array().filter(|m| !m.is_empty()) {
+        html.push_str("<h2>Dual-stack mismatches</h2>\n<ul>\n");
+        for mismatch in mismatches {
+            html.push_str(&format!(
+                "<li>{} <span class=\"skipped\">(only works over {})</span></li>\n",
+                escape(mismatch["url"].as_str().unwrap_or("")),
+                escape(mismatch["works_over"].as_str().unwrap_or(""))
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    // Results table
+    html.push_str("<h2>Results</h2>\n<div class=\"filters\">\n");
+    html.push_str("<input id=\"search\" type=\"search\" placeholder=\"Filter by URL, status, reason, tag...\">\n");
+    html.push_str(
+        "<select id=\"state\"><option value=\"all\">All results</option><option value=\"down\">Failed</option>\
+         <option value=\"up\">Successful</option><option value=\"skipped\">Skipped</option></select>\n</div>\n",
+    );
+    html.push_str(
+        "<table id=\"results\">\n<thead><tr><th>URL</th><th>Status</th><th>Category</th><th>Reason</th>\
+         <th class=\"num\">Time (ms)</th><th class=\"num\">Size</th><th>Remote</th><th>HTTP</th><th>Tags</th>\
+         <th>Checked</th></tr></thead>\n<tbody>\n",
+    );
+    for row in rows {
+        let state = if row.status == "SKIPPED" {
+            "skipped"
+        } else if row.is_up() {
+            "up"
+        } else {
+            "down"
+        };
+        let url = if row.step.is_empty() { row.url.clone() } else { format!("{} › {} ({})", row.scenario, row.step, row.url) };
+        let reason = if row.assertions.is_empty() { row.reason.clone() } else { format!("{}: {}", row.reason, row.assertions) };
+        html.push_str(&format!(
+            "<tr data-state=\"{}\"><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td class=\"reason\">{}</td>\
+             <td class=\"num\">{}</td><td class=\"num\" data-sort=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            state,
+            escape(&url),
+            state,
+            escape(&row.status),
+            category::of(row).map(|c| c.label()).unwrap_or(""),
+            escape(&reason),
+            row.time_ms,
+            row.size_bytes,
+            format_size(row.size_bytes),
+            escape(&row.remote_addr),
+            escape(&row.http_version),
+            escape(&row.tags),
+            escape(&row.timestamp)
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html.push_str(&format!("<script>{}</script>\n</body>\n</html>\n", SCRIPT));
+    html
+}
+
+/// Renders a breakdown table with proportional bars
+fn breakdown(title: &str, entries: &[(String, u64, bool)]) -> String {
+    let mut html = format!("<div class=\"panel\"><h2>{}</h2>\n", escape(title));
+    if entries.is_empty() {
+        html.push_str("<p class=\"muted\">None</p></div>\n");
+        return html;
+    }
+    let peak = entries.iter().map(|(_, count, _)| *count).max().unwrap_or(1).max(1);
+    html.push_str("<table>\n");
+    for (label, count, up) in entries {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"num\">{}</td><td style=\"width: 60%\"><span class=\"bar{}\" style=\"width: {:.1}%\"></span></td></tr>\n",
+            escape(label),
+            count,
+            if *up { "" } else { " down" },
+            *count as f64 / peak as f64 * 100.0
+        ));
+    }
+    html.push_str("</table></div>\n");
+    html
+}
+
+/// Renders the response time histogram as an inline SVG bar chart
+fn latency_chart(histogram: &Value) -> String {
+    let buckets: Vec<(u64, u64, u64)> = histogram
+        .as_array()
+        .map(|b| {
+            b.iter()
+                .map(|b| {
+                    (
+                        b["from_ms"].as_u64().unwrap_or(0),
+                        b["to_ms"].as_u64().unwrap_or(0),
+                        b["count"].as_u64().unwrap_or(0),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut html = String::from("<div class=\"panel\"><h2>Response time distribution</h2>\n");
+    if buckets.is_empty() {
+        html.push_str("<p class=\"muted\">No timed responses</p></div>\n");
+        return html;
+    }
+
+    let (width, height, bar_gap) = (480.0, 200.0, 4.0);
+    let peak = buckets.iter().map(|(_, _, count)| *count).max().unwrap_or(1).max(1) as f64;
+    let bar_width = width / buckets.len() as f64;
+    html.push_str(&format!(
+        "<svg viewBox=\"0 0 {} {}\" width=\"100%\" role=\"img\" aria-label=\"Response time histogram\">\n",
+        width,
+        height + 40.0
+    ));
+    for (i, (from, to, count)) in buckets.iter().enumerate() {
+        let bar_height = *count as f64 / peak * height;
+        let x = i as f64 * bar_width;
+        html.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#0969da\"><title>{}–{} ms: {}</title></rect>\n",
+            x + bar_gap / 2.0,
+            height - bar_height,
+            bar_width - bar_gap,
+            bar_height,
+            from,
+            to,
+            count
+        ));
+        html.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" text-anchor=\"middle\" fill=\"#6e7781\">{}</text>\n",
+            x + bar_width / 2.0,
+            height + 14.0,
+            from
+        ));
+    }
+    html.push_str(&format!(
+        "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"11\" text-anchor=\"middle\" fill=\"#6e7781\">ms</text>\n</svg></div>\n",
+        width / 2.0,
+        height + 34.0
+    ));
+    html
+}
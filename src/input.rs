@@ -0,0 +1,123 @@
+// Loading of check targets from plain-text or structured (JSON) input files
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// A single URL to check, together with its per-URL overrides
+/// Plain-text input produces targets with only `url` set
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Target {
+    pub url: String,                          // The URL to check
+    #[serde(default)]
+    pub proxy: Option<String>,                // Proxy override ("none"/"direct" disables the global proxy)
+    #[serde(default, deserialize_with = "crate::timeouts::deserialize_opt")]
+    pub timeout: Option<Duration>,            // Total timeout override
+    #[serde(default, deserialize_with = "crate::timeouts::deserialize_opt")]
+    pub connect_timeout: Option<Duration>,    // Connect timeout override
+    #[serde(default, deserialize_with = "crate::timeouts::deserialize_opt")]
+    pub first_byte_timeout: Option<Duration>, // Time-to-first-byte timeout override
+    #[serde(default, deserialize_with = "crate::timeouts::deserialize_opt")]
+    pub read_timeout: Option<Duration>,       // Idle body read timeout override
+    #[serde(default)]
+    pub method: Option<String>,               // HTTP method (GET if absent)
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,      // Inline body: a string is sent as-is, anything else as JSON
+    #[serde(default)]
+    pub body_file: Option<String>,            // File whose contents are sent as the body
+    #[serde(default)]
+    pub form: Option<BTreeMap<String, String>>, // Form fields, sent form-encoded
+    #[serde(default)]
+    pub content_type: Option<String>,         // Content-Type override
+    #[serde(default, deserialize_with = "crate::timeouts::deserialize_opt")]
+    pub interval: Option<Duration>,           // Re-check interval in watch mode
+    #[serde(default)]
+    pub tags: Vec<String>,                    // Free-form labels for grouping and routing (e.g. "payments")
+}
+
+/// One entry of a structured input file: either a bare URL string
+/// or an object with per-URL settings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TargetSpec {
+    Url(String),
+    Full(Box<Target>),
+}
+
+/// Loads the list of targets to check from the input file
+/// Files ending in `.json` are parsed as a structured array of URL strings
+/// and/or objects; anything else is read as one URL per line
+///
+/// # Arguments
+/// * `path` - Path to the input file
+///
+/// # Returns
+/// * `Result<Vec<Target>>` - Targets with non-empty URLs or an error
+pub fn load_targets(path: &str) -> Result<Vec<Target>> {
+    let targets = if path.to_lowercase().ends_with(".json") {
+        let data = std::fs::read_to_string(path)?;
+        let specs: Vec<TargetSpec> = serde_json::from_str(&data)
+            .with_context(|| format!("Invalid structured input in {}", path))?;
+        specs
+            .into_iter()
+            .map(|spec| match spec {
+                TargetSpec::Url(url) => Target { url, ..Default::default() },
+                TargetSpec::Full(target) => *target,
+            })
+            .collect::<Vec<_>>()
+    } else {
+        read_lines(path)?
+            .into_iter()
+            .map(|url| Target { url, ..Default::default() })
+            .collect::<Vec<_>>()
+    };
+
+    // Trim whitespace and drop empty entries
+    Ok(targets
+        .into_iter()
+        .filter_map(|mut t| {
+            t.url = t.url.trim().to_string();
+            if t.url.is_empty() {
+                None
+            } else {
+                Some(t)
+            }
+        })
+        .collect())
+}
+
+/// Reads a file line by line and returns a vector of strings
+///
+/// # Arguments
+/// * `filename` - Path to the file to read
+///
+/// # Returns
+/// * `Result<Vec<String>>` - Vector of lines or an error
+#[allow(clippy::lines_filter_map_ok)]
+fn read_lines<P>(filename: P) -> Result<Vec<String>>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(&filename)?;
+    let reader = io::BufReader::new(file);
+    // Unreadable lines (e.g. invalid UTF-8) are skipped; the lines after them are still read
+    Ok(reader.lines().filter_map(|l| l.ok()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let path = std::env::temp_dir().join(format!("url-checker-input-{}.txt", std::process::id()));
+        std::fs::write(&path, b"https://a.example.com\n  \nhttps://b\xff.example.com\nhttps://c.example.com\n").unwrap();
+        let targets = load_targets(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(targets.iter().map(|t| t.url.as_str()).collect::<Vec<_>>(), ["https://a.example.com", "https://c.example.com"]);
+    }
+}
@@ -0,0 +1,214 @@
+// JUnit XML export: every check is a testcase so CI systems show broken URLs as test failures
+use std::collections::BTreeMap;
+
+use crate::ResultRow;
+
+/// How testcases are grouped into testsuites
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SuiteBy {
+    Host, // One testsuite per host name
+    Tag,  // One testsuite per first tag, "untagged" for URLs without tags
+}
+
+/// Renders result rows as a JUnit XML document
+/// Scenario steps form one testsuite per scenario; the scenario summary rows are left out
+/// because their steps are already reported
+///
+/// # Arguments
+/// * `rows` - Result rows of the run
+/// * `suite_by` - Grouping of URL checks into testsuites
+///
+/// # Returns
+/// * `String` - XML document
+pub fn render(rows: &[ResultRow], suite_by: SuiteBy) -> String {
+    let mut suites: BTreeMap<String, Vec<&ResultRow>> = BTreeMap::new();
+    for row in rows.iter().filter(|r| !r.is_scenario_summary()) {
+        suites.entry(suite_name(row, suite_by)).or_default().push(row);
+    }
+
+    let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let mut body = String::new();
+    let mut totals = Counts::default();
+    for (name, rows) in &suites {
+        let counts = Counts::of(rows);
+        body.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{}\">\n",
+            escape(name),
+            counts.tests,
+            counts.failures,
+            counts.errors,
+            counts.skipped,
+            counts.time_ms as f64 / 1000.0,
+            timestamp
+        ));
+        for row in rows {
+            body.push_str(&testcase(row, name));
+        }
+        body.push_str("  </testsuite>\n");
+        totals.add(&counts);
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"url-checker\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n{}</testsuites>\n",
+        totals.tests,
+        totals.failures,
+        totals.errors,
+        totals.skipped,
+        totals.time_ms as f64 / 1000.0,
+        body
+    )
+}
+
+/// Testsuite a row belongs to
+fn suite_name(row: &ResultRow, suite_by: SuiteBy) -> String {
+    if !row.scenario.is_empty() {
+        return format!("scenario:{}", row.scenario);
+    }
+    match suite_by {
+        SuiteBy::Host => reqwest::Url::parse(&row.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_else(|| row.url.clone()),
+        SuiteBy::Tag => row.tags.split(',').find(|t| !t.is_empty()).unwrap_or("untagged").to_string(),
+    }
+}
+
+/// Renders one testcase; failed checks carry status, reason and failed assertions
+fn testcase(row: &ResultRow, suite: &str) -> String {
+    let name = if row.step.is_empty() { row.url.clone() } else { format!("{} ({})", row.step, row.url) };
+    let open = format!(
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+        escape(&name),
+        escape(suite),
+        row.time_ms as f64 / 1000.0
+    );
+
+    let mut details = vec![format!("Status: {}", row.status), format!("Reason: {}", row.reason)];
+    if !row.assertions.is_empty() {
+        details.push("Failed assertions:".to_string());
+        details.extend(row.assertions.split("; ").map(|a| format!("  - {}", a)));
+    }
+    if !row.remote_addr.is_empty() {
+        details.push(format!("Remote address: {}", row.remote_addr));
+    }
+    details.push(format!("Checked: {}", row.timestamp));
+    let details = escape(&details.join("\n"));
+
+    let message = if row.assertions.is_empty() {
+        format!("{} {}", row.status, row.reason)
+    } else {
+        format!("{} {}: {}", row.status, row.reason, row.assertions)
+    };
+
+    match row.status.as_str() {
+        "SKIPPED" => format!("{}>\n      <skipped message=\"{}\"/>\n    </testcase>\n", open, escape(&row.reason)),
+        "ERROR" => format!(
+            "{}>\n      <error message=\"{}\" type=\"ERROR\">{}</error>\n    </testcase>\n",
+            open,
+            escape(&message),
+            details
+        ),
+        _ if !row.is_up() => format!(
+            "{}>\n      <failure message=\"{}\" type=\"{}\">{}</failure>\n    </testcase>\n",
+            open,
+            escape(&message),
+            escape(&row.status),
+            details
+        ),
+        _ => format!("{}/>\n", open),
+    }
+}
+
+/// Testcase counts of a testsuite
+#[derive(Default)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+    time_ms: u128,
+}
+
+impl Counts {
+    fn of(rows: &[&ResultRow]) -> Self {
+        let mut counts = Counts { tests: rows.len(), ..Counts::default() };
+        for row in rows {
+            match row.status.as_str() {
+                "SKIPPED" => counts.skipped += 1,
+                "ERROR" => counts.errors += 1,
+                _ if !row.is_up() => counts.failures += 1,
+                _ => {}
+            }
+            counts.time_ms += row.time_ms;
+        }
+        counts
+    }
+
+    fn add(&mut self, other: &Counts) {
+        self.tests += other.tests;
+        self.failures += other.failures;
+        self.errors += other.errors;
+        self.skipped += other.skipped;
+        self.time_ms += other.time_ms;
+    }
+}
+
+/// Escapes text for XML attributes and content, dropping characters XML cannot carry
+fn escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t' | '\r'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_escaped_for_xml() {
+        let rows = [ResultRow {
+            url: "http://example.com/search?q=<a>&lang='en'".to_string(),
+            status: "500".to_string(),
+            reason: "Internal \"Server\" Error\u{1b}[0m".to_string(),
+            assertions: "body contains <ok> & more".to_string(),
+            tags: "a&b".to_string(),
+            ..Default::default()
+        }];
+        let xml = render(&rows, SuiteBy::Tag);
+
+        assert!(xml.contains("<testsuite name=\"a&amp;b\" tests=\"1\" failures=\"1\" errors=\"0\" skipped=\"0\""));
+        assert!(xml.contains(
+            "<testcase name=\"http://example.com/search?q=&lt;a&gt;&amp;lang=&apos;en&apos;\" classname=\"a&amp;b\""
+        ));
+        assert!(xml.contains(
+            "<failure message=\"500 Internal &quot;Server&quot; Error[0m: body contains &lt;ok&gt; &amp; more\" type=\"500\">"
+        ));
+        assert!(xml.contains("  - body contains &lt;ok&gt; &amp; more\n"));
+        // Control characters are not allowed in XML 1.0
+        assert!(!xml.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn outcomes_map_to_testcase_elements() {
+        let row = |url: &str, status: &str| ResultRow {
+            url: url.to_string(),
+            status: status.to_string(),
+            reason: "why".to_string(),
+            ..Default::default()
+        };
+        let rows = [row("http://a.test/", "200"), row("http://a.test/x", "ERROR"), row("http://b.test/", "SKIPPED")];
+        let xml = render(&rows, SuiteBy::Host);
+
+        assert!(xml.contains("<testsuites name=\"url-checker\" tests=\"3\" failures=\"0\" errors=\"1\" skipped=\"1\""));
+        assert!(xml.contains("<testsuite name=\"a.test\" tests=\"2\""));
+        assert!(xml.contains("<testcase name=\"http://a.test/\" classname=\"a.test\" time=\"0.000\"/>"));
+        assert!(xml.contains("<error message=\"ERROR why\" type=\"ERROR\">"));
+        assert!(xml.contains("<skipped message=\"why\"/>"));
+    }
+}
@@ -0,0 +1,137 @@
+// Markdown report for PR comments and wiki pages: summary table and collapsible failure lists
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::category::{self, Category};
+use crate::{ResultRow, Stats};
+
+/// Maximum size of a GitHub comment body is 65536 characters; stay a little below it
+const MAX_LEN: usize = 65_000;
+
+/// Room kept free for section headers, closing tags and the note on omitted rows
+const RESERVE: usize = 2_000;
+
+/// Renders the Markdown report
+/// Rows that would push the document past the size of a GitHub comment are left out and
+/// counted in a note instead
+///
+/// # Arguments
+/// * `stats` - Statistics of the rows
+/// * `rows` - Result rows of the run
+/// * `apdex_threshold` - Satisfied response time for the Apdex score
+/// * `failures_only` - Leave the table of all results out
+///
+/// # Returns
+/// * `String` - Markdown document
+pub fn render(stats: &Stats, rows: &[ResultRow], apdex_threshold: Duration, failures_only: bool) -> String {
+    let mut md = String::from("## URL Checker Report\n\n");
+    md.push_str("| Statistic | Value |\n| --- | --- |\n");
+    for (label, value) in stats.summary(apdex_threshold) {
+        md.push_str(&format!("| {} | {} |\n", label, cell(&value)));
+    }
+    md.push_str(&format!(
+        "\n_Generated {}_\n\n",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+    ));
+
+    // Scenario steps carry the cause of a failed scenario, so summary rows are not listed again;
+    // skipped checks are not failures and only appear in the table of all results
+    let mut by_category: BTreeMap<Category, Vec<&ResultRow>> = BTreeMap::new();
+    for row in rows.iter().filter(|r| r.is_failure() && !r.is_scenario_summary()) {
+        if let Some(category) = category::of(row) {
+            by_category.entry(category).or_default().push(row);
+        }
+    }
+    let failures: usize = by_category.values().map(|r| r.len()).sum();
+
+    let mut omitted = 0;
+    if failures == 0 {
+        md.push_str(&format!("### ✅ All {} check(s) passed\n\n", stats.total - stats.skipped));
+    } else {
+        md.push_str(&format!("### ❌ Failures ({})\n\n", failures));
+        for (category, rows) in &by_category {
+            md.push_str(&format!(
+                "<details>\n<summary><b>{}</b> ({})</summary>\n\n| URL | Status | Reason | Time (ms) |\n| --- | --- | --- | ---: |\n",
+                category.label(),
+                rows.len()
+            ));
+            for row in rows {
+                let reason = if row.assertions.is_empty() { row.reason.clone() } else { row.assertions.clone() };
+                let line = format!("| {} | {} | {} | {} |\n", cell(&name(row)), cell(&row.status), cell(&reason), row.time_ms);
+                omitted += push_within_limit(&mut md, &line);
+            }
+            md.push_str("\n</details>\n\n");
+        }
+    }
+
+    if !failures_only {
+        md.push_str(&format!(
+            "<details>\n<summary>All results ({})</summary>\n\n| URL | Status | Time (ms) | Result |\n| --- | --- | ---: | --- |\n",
+            rows.len()
+        ));
+        for row in rows {
+            let result = if row.is_up() { "✅" } else if row.status == "SKIPPED" { "⏱" } else { "❌" };
+            let line = format!("| {} | {} | {} | {} |\n", cell(&name(row)), cell(&row.status), row.time_ms, result);
+            omitted += push_within_limit(&mut md, &line);
+        }
+        md.push_str("\n</details>\n");
+    }
+
+    if omitted > 0 {
+        md.push_str(&format!("\n_{} row(s) omitted to fit in a GitHub comment._\n", omitted));
+    }
+    md
+}
+
+/// Appends a table row unless it would make the document too long
+///
+/// # Returns
+/// * `usize` - 1 if the row was left out, 0 otherwise
+fn push_within_limit(md: &mut String, line: &str) -> usize {
+    if md.len() + line.len() > MAX_LEN - RESERVE {
+        return 1;
+    }
+    md.push_str(line);
+    0
+}
+
+/// Name a row is listed under: the URL, or scenario and step for scenario steps
+fn name(row: &ResultRow) -> String {
+    if row.step.is_empty() {
+        row.url.clone()
+    } else {
+        format!("{} › {} ({})", row.scenario, row.step, row.url)
+    }
+}
+
+/// Escapes text for a Markdown table cell
+pub fn cell(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('|', "\\|")
+        .replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_checks_are_not_failures() {
+        let row = |url: &str, status: &str| ResultRow {
+            url: url.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        let rows = [row("http://a.test/", "200"), row("http://b.test/", "SKIPPED")];
+        let md = render(&Stats::from_rows(&rows), &rows, Duration::from_millis(500), false);
+        assert!(md.contains("### ✅ All 1 check(s) passed"));
+        assert!(md.contains("| http://b.test/ | SKIPPED | 0 | ⏱ |"));
+
+        let rows = [row("http://a.test/", "503"), row("http://b.test/", "SKIPPED")];
+        let md = render(&Stats::from_rows(&rows), &rows, Duration::from_millis(500), true);
+        assert!(md.contains("### ❌ Failures (1)"));
+        assert!(!md.contains("http://b.test/"));
+    }
+}
@@ -0,0 +1,48 @@
+// Newline-delimited JSON output: one line per result as it completes, then a summary line
+use std::io::{LineWriter, Write};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::{output, ResultRow};
+
+/// Writes result and summary lines, flushing after every line so the file can be tailed
+pub struct Writer {
+    out: LineWriter<Box<dyn Write>>,
+    path: String,
+}
+
+impl Writer {
+    /// Wraps an open destination
+    pub fn new(out: Box<dyn Write>, path: &str) -> Self {
+        Writer { out: LineWriter::new(out), path: path.to_string() }
+    }
+
+    /// Creates (truncates) the output file, or writes to stdout for `-`
+    pub fn create(path: &str) -> Result<Self> {
+        Ok(Writer::new(output::create(path)?, path))
+    }
+
+    /// Opens the output file for appending, as watch mode does across cycles
+    pub fn append(path: &str) -> Result<Self> {
+        Ok(Writer::new(output::append(path)?, path))
+    }
+
+    /// Writes one result line: the row's fields with `"type": "result"`
+    pub fn result(&mut self, row: &ResultRow) -> Result<()> {
+        let mut line = serde_json::to_value(row)?;
+        line["type"] = "result".into();
+        self.line(&line)
+    }
+
+    /// Writes the summary line: the report metadata with `"type": "summary"`
+    pub fn summary(&mut self, metadata: &Value) -> Result<()> {
+        let mut line = metadata.clone();
+        line["type"] = "summary".into();
+        self.line(&line)
+    }
+
+    fn line(&mut self, value: &Value) -> Result<()> {
+        writeln!(self.out, "{}", serde_json::to_string(value)?).with_context(|| format!("Could not write to {}", self.path))
+    }
+}
@@ -0,0 +1,178 @@
+// Report destinations: `--report FORMAT=PATH` specs, stdout reports, and where the
+// decorative terminal output goes when a report is written to stdout
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+
+use crate::{custom, html, junit, markdown, ndjson, prometheus, report_document, report_metadata, Args, Format, ResultRow, Stats};
+
+/// Prints a line of decorative output (banner, tables, statistics) wherever `set_decor` sent it
+macro_rules! say {
+    () => {
+        $crate::output::decor_line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::decor_line(format_args!($($arg)*))
+    };
+}
+
+/// A report to write, given as `FORMAT=PATH` on the command line; `-` is stdout
+#[derive(Debug, Clone)]
+pub struct ReportSpec {
+    pub format: Format, // Report format
+    pub path: String,   // Output file, or "-" for stdout
+}
+
+impl FromStr for ReportSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (format, path) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected FORMAT=PATH, got '{}'", s))?;
+        let format = Format::from_str(format, true).map_err(|_| {
+            let names = Format::value_variants().iter().map(|f| f.as_str()).collect::<Vec<_>>();
+            anyhow!("unknown report format '{}' (expected one of: {})", format, names.join(", "))
+        })?;
+        if path.is_empty() {
+            return Err(anyhow!("missing path in '{}' (use - for stdout)", s));
+        }
+        Ok(ReportSpec { format, path: path.to_string() })
+    }
+}
+
+impl ReportSpec {
+    /// Whether the report goes to stdout
+    pub fn is_stdout(&self) -> bool {
+        self.path == "-"
+    }
+
+    /// Path for display, "stdout" for `-`
+    pub fn display_path(&self) -> &str {
+        if self.is_stdout() {
+            "stdout"
+        } else {
+            &self.path
+        }
+    }
+}
+
+/// Where decorative output goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decor {
+    Stdout, // Normal terminal output
+    Stderr, // Moved aside because a report is written to stdout
+    Off,    // Disabled with --quiet
+}
+
+static DECOR: AtomicU8 = AtomicU8::new(0);
+
+/// Sends decorative output to stdout, stderr or nowhere
+/// When it moves to stderr, colors follow whether stderr is a terminal
+pub fn set_decor(decor: Decor) {
+    if decor == Decor::Stderr {
+        colored::control::set_override(std::io::IsTerminal::is_terminal(&std::io::stderr()));
+    }
+    DECOR.store(decor as u8, Ordering::Relaxed);
+}
+
+/// Current destination of decorative output
+pub fn decor() -> Decor {
+    match DECOR.load(Ordering::Relaxed) {
+        0 => Decor::Stdout,
+        1 => Decor::Stderr,
+        _ => Decor::Off,
+    }
+}
+
+/// Prints one line of decorative output; used through the `say!` macro
+pub fn decor_line(args: fmt::Arguments) {
+    match decor() {
+        Decor::Stdout => println!("{}", args),
+        Decor::Stderr => eprintln!("{}", args),
+        Decor::Off => {}
+    }
+}
+
+/// Opens a report destination for writing, truncating files
+pub fn create(path: &str) -> Result<Box<dyn Write>> {
+    if path == "-" {
+        return Ok(Box::new(std::io::stdout()));
+    }
+    let file = File::create(path).with_context(|| format!("Could not create {} for writing", path))?;
+    Ok(Box::new(file))
+}
+
+/// Opens a report destination for appending, as watch mode does across cycles
+pub fn append(path: &str) -> Result<Box<dyn Write>> {
+    if path == "-" {
+        return Ok(Box::new(std::io::stdout()));
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open {} for appending", path))?;
+    Ok(Box::new(file))
+}
+
+/// Writes a complete report
+///
+/// # Arguments
+/// * `spec` - Format and destination
+/// * `args` - Command-line arguments with the format options
+/// * `stats` - Statistics of the rows
+/// * `mismatches` - URLs that only work over one address family
+/// * `rows` - All result rows
+///
+/// # Returns
+/// * `Result<()>` - Ok or an error writing the report
+pub fn write(spec: &ReportSpec, args: &Args, stats: &Stats, mismatches: &[(String, String)], rows: &[ResultRow]) -> Result<()> {
+    // Metrics files are replaced atomically instead of being written in place
+    if spec.format == Format::Prometheus {
+        return prometheus::write_textfile(&spec.path, &prometheus::Metrics::from_rows(rows).render());
+    }
+    let mut out = create(&spec.path)?;
+    let written: Result<()> = match spec.format {
+        Format::Csv => {
+            let mut wtr = csv::Writer::from_writer(out);
+            for row in rows {
+                wtr.serialize(row)?;
+            }
+            wtr.flush().map_err(Into::into)
+        }
+        Format::Json => {
+            let document = report_document(stats, mismatches, rows, args.apdex_threshold);
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?).map_err(Into::into)
+        }
+        Format::Ndjson => {
+            let mut writer = ndjson::Writer::new(out, &spec.path);
+            for row in rows {
+                writer.result(row)?;
+            }
+            writer.summary(&report_metadata(stats, mismatches, args.apdex_threshold))
+        }
+        Format::Junit => write!(out, "{}", junit::render(rows, args.junit_suites)).map_err(Into::into),
+        Format::Html => {
+            let document = report_document(stats, mismatches, rows, args.apdex_threshold);
+            write!(out, "{}", html::render(&document, rows)).map_err(Into::into)
+        }
+        Format::Markdown => {
+            let markdown = markdown::render(stats, rows, args.apdex_threshold, args.markdown_failures_only);
+            write!(out, "{}", markdown).map_err(Into::into)
+        }
+        Format::Template => {
+            let path = args.template.as_deref().context("The template format needs --template <FILE>")?;
+            let metadata = report_metadata(stats, mismatches, args.apdex_threshold);
+            let rendered = custom::Template::load(path)?.render(&metadata, rows)?;
+            write!(out, "{}", rendered).map_err(Into::into)
+        }
+        Format::Prometheus => unreachable!("written by prometheus::write_textfile"),
+    };
+    written.with_context(|| format!("Could not write {} report to {}", spec.format.as_str(), spec.display_path()))
+}
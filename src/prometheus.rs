@@ -0,0 +1,357 @@
+// Prometheus text exposition: a textfile-collector report for one-shot runs and the
+// /metrics endpoint of watch mode
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use colored::Colorize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::category::{self, Category};
+use crate::{output, parse_timestamp, ResultRow};
+
+/// Upper bounds of the response time histogram buckets, in seconds
+const BUCKETS: [f64; 10] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Content type of the text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Largest request head the /metrics endpoint reads
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Longest pause before accepting again after accept errors (e.g. out of file descriptors)
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Labels identifying one checked URL (or scenario step)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Labels {
+    url: String,       // Checked URL, "scenario:NAME" for scenario summaries
+    scenario: String,  // Scenario the check belongs to, empty for URL checks
+    step: String,      // Scenario step, empty for URL checks and scenario summaries
+    ip_family: String, // "IPv4"/"IPv6", only set when a URL is checked over several families or addresses
+    address: String,   // Remote address, only set when a URL is checked over several addresses of a family
+    tags: String,      // Tags of the URL or scenario (","-separated)
+}
+
+/// Metrics of one series, accumulated over every check of it
+#[derive(Debug, Default)]
+struct Series {
+    up: bool,                         // Whether the latest check succeeded
+    status: u16,                      // HTTP status of the latest check, 0 without a response
+    checked_at: i64,                  // Time of the latest check (Unix seconds)
+    cert_expires: Option<i64>,        // Certificate expiry seen by the latest check (Unix seconds)
+    checks: u64,                      // Number of checks
+    buckets: [u64; BUCKETS.len()],    // Responses per histogram bucket (not cumulative)
+    time_sum: f64,                    // Sum of response times in seconds
+    time_count: u64,                  // Number of timed responses
+    errors: BTreeMap<Category, u64>,  // Failed checks per error category
+}
+
+/// Metrics of every checked URL, updated with each batch of results
+#[derive(Debug, Default)]
+pub struct Metrics {
+    series: BTreeMap<Labels, Series>,
+}
+
+impl Metrics {
+    /// Metrics of a single run
+    pub fn from_rows(rows: &[ResultRow]) -> Self {
+        let mut metrics = Metrics::default();
+        metrics.observe(rows);
+        metrics
+    }
+
+    /// Adds results: gauges take the latest values, histograms and counters accumulate
+    ///
+    /// # Arguments
+    /// * `rows` - Results to add
+    pub fn observe(&mut self, rows: &[ResultRow]) {
+        // `--dual-stack` and `--each-address` check a URL several times per run; only those rows
+        // are told apart by family and address, so other series survive failed connections
+        // (which have neither) and DNS changes
+        let check = |row: &ResultRow| (row.url.clone(), row.scenario.clone(), row.step.clone());
+        let family = |row: &ResultRow| (check(row), row.ip_family.clone());
+        let (mut checks, mut families) = (HashSet::new(), HashSet::new());
+        let fanned_out: HashSet<_> = rows.iter().map(check).filter(|c| !checks.insert(c.clone())).collect();
+        let per_address: HashSet<_> = rows.iter().map(family).filter(|f| !families.insert(f.clone())).collect();
+
+        for row in rows {
+            let labels = Labels {
+                url: row.url.clone(),
+                scenario: row.scenario.clone(),
+                step: row.step.clone(),
+                ip_family: if fanned_out.contains(&check(row)) { row.ip_family.clone() } else { String::new() },
+                address: if per_address.contains(&family(row)) { row.remote_addr.clone() } else { String::new() },
+                tags: row.tags.clone(),
+            };
+            let series = self.series.entry(labels).or_default();
+            series.up = row.is_up();
+            series.status = row.status.parse().unwrap_or(0);
+            series.checked_at = parse_timestamp(&row.timestamp).unwrap_or_else(Utc::now).timestamp();
+            series.cert_expires = parse_timestamp(&row.cert_expires).map(|t| t.timestamp());
+            series.checks += 1;
+
+            // Only responses (and finished scenarios) have a meaningful response time
+            if row.is_timed() {
+                let seconds = row.time_ms as f64 / 1000.0;
+                if let Some(bucket) = BUCKETS.iter().position(|&le| seconds <= le) {
+                    series.buckets[bucket] += 1;
+                }
+                series.time_sum += seconds;
+                series.time_count += 1;
+            }
+            if let Some(category) = category::of(row) {
+                *series.errors.entry(category).or_default() += 1;
+            }
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        family(&mut out, "url_checker_up", "gauge", "Whether the latest check of the URL succeeded (1) or failed (0)");
+        for (labels, series) in &self.series {
+            sample(&mut out, "url_checker_up", &labels.render(&[]), series.up as u8);
+        }
+
+        family(&mut out, "url_checker_status_code", "gauge", "HTTP status code of the latest check, 0 without a response");
+        for (labels, series) in &self.series {
+            sample(&mut out, "url_checker_status_code", &labels.render(&[]), series.status);
+        }
+
+        family(
+            &mut out,
+            "url_checker_response_time_seconds",
+            "histogram",
+            "Response time of the checks that got a response",
+        );
+        for (labels, series) in &self.series {
+            let mut cumulative = 0;
+            for (le, count) in BUCKETS.iter().zip(series.buckets) {
+                cumulative += count;
+                let le = le.to_string();
+                sample(&mut out, "url_checker_response_time_seconds_bucket", &labels.render(&[("le", &le)]), cumulative);
+            }
+            let all = labels.render(&[("le", "+Inf")]);
+            sample(&mut out, "url_checker_response_time_seconds_bucket", &all, series.time_count);
+            sample(&mut out, "url_checker_response_time_seconds_sum", &labels.render(&[]), series.time_sum);
+            sample(&mut out, "url_checker_response_time_seconds_count", &labels.render(&[]), series.time_count);
+        }
+
+        family(
+            &mut out,
+            "url_checker_cert_expiry_timestamp_seconds",
+            "gauge",
+            "Expiry (notAfter) of the server certificate as a Unix timestamp",
+        );
+        for (labels, series) in &self.series {
+            if let Some(expires) = series.cert_expires {
+                sample(&mut out, "url_checker_cert_expiry_timestamp_seconds", &labels.render(&[]), expires);
+            }
+        }
+
+        family(&mut out, "url_checker_last_check_timestamp_seconds", "gauge", "Time of the latest check as a Unix timestamp");
+        for (labels, series) in &self.series {
+            sample(&mut out, "url_checker_last_check_timestamp_seconds", &labels.render(&[]), series.checked_at);
+        }
+
+        family(&mut out, "url_checker_checks_total", "counter", "Number of checks of the URL");
+        for (labels, series) in &self.series {
+            sample(&mut out, "url_checker_checks_total", &labels.render(&[]), series.checks);
+        }
+
+        // Every category is exported, so rate() sees a counter start from 0
+        family(&mut out, "url_checker_errors_total", "counter", "Failed checks of the URL by error category");
+        for (labels, series) in &self.series {
+            for category in Category::ALL {
+                let count = series.errors.get(&category).copied().unwrap_or(0);
+                sample(&mut out, "url_checker_errors_total", &labels.render(&[("category", category.name())]), count);
+            }
+        }
+        out
+    }
+}
+
+impl Labels {
+    /// Label set in exposition syntax; scenario, step, IP family and address are left out when empty
+    fn render(&self, extra: &[(&str, &str)]) -> String {
+        let mut pairs = vec![("url", self.url.as_str())];
+        if !self.scenario.is_empty() {
+            pairs.push(("scenario", &self.scenario));
+        }
+        if !self.step.is_empty() {
+            pairs.push(("step", &self.step));
+        }
+        if !self.ip_family.is_empty() {
+            pairs.push(("ip_family", &self.ip_family));
+        }
+        if !self.address.is_empty() {
+            pairs.push(("address", &self.address));
+        }
+        pairs.push(("tags", &self.tags));
+        pairs.extend_from_slice(extra);
+        let pairs = pairs
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+            .collect::<Vec<_>>();
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+/// Writes the HELP and TYPE lines of a metric family
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Writes one sample line
+fn sample(out: &mut String, name: &str, labels: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "{}{} {}", name, labels, value);
+}
+
+/// Escapes a label value: backslash, double quote and line feed
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Writes metrics for the textfile collector
+/// Files are written next to the target and renamed over it, so the collector never
+/// reads a half-written file
+///
+/// # Arguments
+/// * `path` - Destination (should end in .prom), or "-" for stdout
+/// * `text` - Rendered metrics
+///
+/// # Returns
+/// * `Result<()>` - Ok or an error writing the file
+pub fn write_textfile(path: &str, text: &str) -> Result<()> {
+    if path == "-" {
+        return write!(output::create(path)?, "{}", text).map_err(Into::into);
+    }
+    let partial = format!("{}.{}.tmp", path, std::process::id());
+    std::fs::write(&partial, text).with_context(|| format!("Could not write {}", partial))?;
+    std::fs::rename(&partial, path).with_context(|| format!("Could not move {} to {}", partial, path))
+}
+
+/// Binds the address the /metrics endpoint listens on
+/// Binding happens before watching starts, so a busy port is reported right away
+pub async fn listen(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Could not listen for metrics on {}", addr))
+}
+
+/// Serves the metrics over HTTP until the task is dropped
+/// GET /metrics returns the exposition; every connection answers one request.
+/// Accept errors are logged and retried with a growing pause, so a persistent one
+/// (e.g. out of file descriptors) doesn't spin
+///
+/// # Arguments
+/// * `listener` - Bound listener from `listen`
+/// * `metrics` - Metrics shared with the watch loop
+pub async fn serve(listener: TcpListener, metrics: Arc<Mutex<Metrics>>) {
+    let mut backoff = Duration::ZERO;
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => {
+                backoff = Duration::ZERO;
+                stream
+            }
+            Err(e) => {
+                backoff = (backoff * 2).clamp(Duration::from_millis(10), MAX_ACCEPT_BACKOFF);
+                eprintln!("{} Metrics endpoint could not accept a connection: {}", "✗".red(), e);
+                tokio::time::sleep(backoff).await;
+                continue;
+            }
+        };
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            let _ = tokio::time::timeout(Duration::from_secs(10), respond(stream, &metrics)).await;
+        });
+    }
+}
+
+/// Reads one request and writes the response
+async fn respond(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&chunk[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("").split('?').next().unwrap_or("");
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET" | "HEAD", "/metrics") => {
+            let text = metrics.lock().map(|m| m.render()).unwrap_or_default();
+            ("200 OK", CONTENT_TYPE, text)
+        }
+        ("GET" | "HEAD", "/") => (
+            "200 OK",
+            "text/html; charset=utf-8",
+            "<html><body><a href=\"/metrics\">Metrics</a></body></html>\n".to_string(),
+        ),
+        ("GET" | "HEAD", _) => ("404 Not Found", "text/plain; charset=utf-8", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method not allowed\n".to_string()),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(family: &str, addr: &str, status: &str) -> ResultRow {
+        ResultRow {
+            url: "https://example.com".to_string(),
+            status: status.to_string(),
+            ip_family: family.to_string(),
+            remote_addr: addr.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn families_and_addresses_get_their_own_series() {
+        let text = Metrics::from_rows(&[
+            row("IPv4", "192.0.2.1:443", "200"),
+            row("IPv6", "[2001:db8::1]:443", "ERROR"),
+        ])
+        .render();
+        assert!(text.contains("url_checker_up{url=\"https://example.com\",ip_family=\"IPv4\",tags=\"\"} 1\n"));
+        assert!(text.contains("url_checker_up{url=\"https://example.com\",ip_family=\"IPv6\",tags=\"\"} 0\n"));
+
+        let text = Metrics::from_rows(&[row("IPv4", "192.0.2.1:443", "200"), row("IPv4", "192.0.2.2:443", "503")]).render();
+        assert!(text.contains("ip_family=\"IPv4\",address=\"192.0.2.1:443\",tags=\"\"} 1\n"));
+        assert!(text.contains("ip_family=\"IPv4\",address=\"192.0.2.2:443\",tags=\"\"} 0\n"));
+
+        // A single row per URL keeps one series whether or not the connection succeeded
+        let mut metrics = Metrics::from_rows(&[row("IPv4", "192.0.2.1:443", "200")]);
+        metrics.observe(&[row("", "", "ERROR")]);
+        assert_eq!(metrics.series.len(), 1);
+        assert!(metrics.render().contains("url_checker_up{url=\"https://example.com\",tags=\"\"} 0\n"));
+    }
+}
@@ -0,0 +1,215 @@
+// Proxy configuration: proxy URL validation, credential handling and NO_PROXY-style bypass lists
+use std::net::IpAddr;
+
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+
+/// Proxy schemes accepted by `--proxy` and per-URL overrides
+const SUPPORTED_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Per-URL proxy values that force a direct connection
+const DIRECT_VALUES: &[&str] = &["none", "direct", "off"];
+
+/// Parses and validates a proxy URL, optionally attaching credentials
+/// Credentials given separately (`user:password`) override any in the URL
+///
+/// # Arguments
+/// * `spec` - Proxy URL such as `http://proxy:3128` or `socks5h://user:pw@host:1080`
+/// * `credentials` - Optional `user:password` pair from `--proxy-user`
+///
+/// # Returns
+/// * `Result<Url>` - Normalized proxy URL or an error
+pub fn parse_proxy(spec: &str, credentials: Option<&str>) -> Result<Url> {
+    let mut url = Url::parse(spec).with_context(|| format!("Invalid proxy URL {}", spec))?;
+    if !SUPPORTED_SCHEMES.contains(&url.scheme()) {
+        bail!(
+            "Unsupported proxy scheme '{}' (expected one of: {})",
+            url.scheme(),
+            SUPPORTED_SCHEMES.join(", ")
+        );
+    }
+    if url.host_str().is_none() {
+        bail!("Proxy URL {} has no host", spec);
+    }
+
+    if let Some(creds) = credentials {
+        let (user, password) = creds.split_once(':').unwrap_or((creds, ""));
+        // set_username/set_password only fail for cannot-be-a-base URLs, which were rejected above
+        let _ = url.set_username(user);
+        let _ = url.set_password(Some(password).filter(|p| !p.is_empty()));
+    }
+    Ok(url)
+}
+
+/// Resolves the proxy to use for a target, taking a per-URL override into account
+///
+/// # Arguments
+/// * `global` - Proxy from `--proxy`, if any
+/// * `override_spec` - Per-URL `proxy` value from structured input, if any
+/// * `credentials` - Optional `user:password` pair from `--proxy-user`
+///
+/// # Returns
+/// * `Result<Option<Url>>` - Proxy URL, or `None` for a direct connection
+pub fn effective_proxy(
+    global: Option<&Url>,
+    override_spec: Option<&str>,
+    credentials: Option<&str>,
+) -> Result<Option<Url>> {
+    match override_spec.map(str::trim) {
+        Some(spec) if DIRECT_VALUES.contains(&spec.to_lowercase().as_str()) => Ok(None),
+        Some(spec) if !spec.is_empty() => parse_proxy(spec, credentials).map(Some),
+        _ => Ok(global.cloned()),
+    }
+}
+
+/// Returns the proxy URL with its password masked, for display and reports
+pub fn redact(url: &Url) -> String {
+    let mut shown = url.clone();
+    if shown.password().is_some() {
+        let _ = shown.set_password(Some("***"));
+    }
+    shown.as_str().trim_end_matches('/').to_string()
+}
+
+/// A NO_PROXY-style bypass list
+/// Entries are comma-separated host names (matching the host and its subdomains,
+/// an optional leading `.` is ignored), IP addresses, CIDR ranges, optionally
+/// followed by `:port`, or `*` to bypass the proxy for every host
+#[derive(Debug, Clone, Default)]
+pub struct NoProxy {
+    entries: Vec<BypassEntry>,
+}
+
+#[derive(Debug, Clone)]
+enum BypassEntry {
+    All,
+    Domain(String, Option<u16>),
+    Ip(IpAddr, Option<u16>),
+    Cidr(IpAddr, u8),
+}
+
+impl NoProxy {
+    /// Parses a comma-separated bypass list, ignoring empty entries
+    pub fn parse(list: &str) -> Self {
+        let entries = list
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(|entry| {
+                if entry == "*" {
+                    return BypassEntry::All;
+                }
+                if let Some((net, bits)) = entry.split_once('/') {
+                    if let (Ok(net), Ok(bits)) = (net.parse::<IpAddr>(), bits.parse::<u8>()) {
+                        return BypassEntry::Cidr(net, bits);
+                    }
+                }
+                let (host, port) = split_host_port(entry);
+                match host.parse::<IpAddr>() {
+                    Ok(ip) => BypassEntry::Ip(ip, port),
+                    Err(_) => BypassEntry::Domain(host.trim_start_matches('.').to_lowercase(), port),
+                }
+            })
+            .collect();
+        NoProxy { entries }
+    }
+
+    /// Returns true if requests to `url` should bypass the proxy
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+        let port = url.port_or_known_default();
+        let ip = host.parse::<IpAddr>().ok();
+
+        self.entries.iter().any(|entry| match entry {
+            BypassEntry::All => true,
+            BypassEntry::Domain(domain, p) => {
+                port_matches(*p, port)
+                    && (host == *domain || host.ends_with(&format!(".{}", domain)))
+            }
+            BypassEntry::Ip(addr, p) => port_matches(*p, port) && ip == Some(*addr),
+            BypassEntry::Cidr(net, bits) => ip.is_some_and(|ip| in_cidr(ip, *net, *bits)),
+        })
+    }
+}
+
+/// Splits an optional `:port` suffix off a bypass entry, leaving bare IPv6 addresses intact
+fn split_host_port(entry: &str) -> (&str, Option<u16>) {
+    if let Some(rest) = entry.strip_prefix('[') {
+        if let Some((host, tail)) = rest.split_once(']') {
+            return (host, tail.strip_prefix(':').and_then(|p| p.parse().ok()));
+        }
+    }
+    match entry.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => match port.parse() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (entry, None),
+        },
+        _ => (entry, None),
+    }
+}
+
+fn port_matches(expected: Option<u16>, actual: Option<u16>) -> bool {
+    expected.is_none() || expected == actual
+}
+
+fn in_cidr(ip: IpAddr, net: IpAddr, bits: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) if bits <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - bits as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) if bits <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - bits as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bypassed(list: &str, url: &str) -> bool {
+        NoProxy::parse(list).matches(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn cidr_entries_match_addresses_in_the_network() {
+        assert!(bypassed("10.0.0.0/8", "http://10.1.2.3/"));
+        assert!(!bypassed("10.0.0.0/8", "http://11.0.0.1/"));
+        assert!(bypassed("192.168.1.0/24", "https://192.168.1.255:8443/"));
+        assert!(bypassed("0.0.0.0/0", "http://203.0.113.9/"));
+        assert!(bypassed("fd00::/8", "http://[fd12::1]/"));
+        assert!(!bypassed("fd00::/8", "http://[fe80::1]/"));
+        // Host names and addresses of the other family are never in a network
+        assert!(!bypassed("10.0.0.0/8", "http://internal.example/"));
+        assert!(!bypassed("::/0", "http://10.0.0.1/"));
+        assert!(!in_cidr("10.0.0.1".parse().unwrap(), "10.0.0.0".parse().unwrap(), 33));
+    }
+
+    #[test]
+    fn ports_restrict_host_and_address_entries() {
+        assert!(bypassed("example.com:8080", "http://example.com:8080/"));
+        assert!(!bypassed("example.com:8080", "http://example.com/"));
+        // Known default ports count as the URL's port
+        assert!(bypassed("example.com:443", "https://example.com/"));
+        assert!(bypassed("127.0.0.1:80", "http://127.0.0.1/"));
+        assert!(!bypassed("127.0.0.1:80", "http://127.0.0.1:81/"));
+        assert!(bypassed("[::1]:8080", "http://[::1]:8080/"));
+        assert!(bypassed("::1", "http://[::1]:9000/"));
+    }
+
+    #[test]
+    fn domains_match_themselves_and_their_subdomains() {
+        assert!(bypassed("example.com", "http://example.com/"));
+        assert!(bypassed("example.com", "http://api.EXAMPLE.com/"));
+        assert!(bypassed(".example.com", "http://example.com/"));
+        assert!(!bypassed("example.com", "http://badexample.com/"));
+        assert!(bypassed(" , other.org, *", "http://anything.test/"));
+        assert!(!bypassed("", "http://example.com/"));
+    }
+}
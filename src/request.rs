@@ -0,0 +1,99 @@
+// Request method, body and Content-Type for a check, with templated payloads
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use reqwest::{Method, RequestBuilder};
+
+use crate::template;
+
+/// Body sent with a check
+#[derive(Debug, Clone)]
+pub enum Body {
+    Json(serde_json::Value),        // JSON document, strings inside are templated
+    Text(String),                   // Raw text (inline or from a file), templated as a whole
+    Form(Vec<(String, String)>),    // Form fields, values are templated
+}
+
+/// How to send the request for a check
+#[derive(Debug, Clone)]
+pub struct RequestSpec {
+    pub method: Method,                 // HTTP method, GET by default
+    pub body: Option<Body>,             // Request body, if any
+    pub content_type: Option<String>,   // Content-Type override
+}
+
+impl Default for RequestSpec {
+    fn default() -> Self {
+        RequestSpec { method: Method::GET, body: None, content_type: None }
+    }
+}
+
+impl RequestSpec {
+    /// Builds a request spec from per-URL settings
+    ///
+    /// # Arguments
+    /// * `method` - HTTP method name, GET when absent
+    /// * `body` - Inline body: a JSON string is sent as text, anything else as JSON
+    /// * `body_file` - File whose contents are sent as the body
+    /// * `form` - Form fields, sent form-encoded
+    /// * `content_type` - Content-Type override (form bodies are always form-encoded)
+    ///
+    /// # Returns
+    /// * `Result<RequestSpec>` - Request spec or an error for an invalid method or unreadable file
+    pub fn new(
+        method: Option<&str>,
+        body: Option<&serde_json::Value>,
+        body_file: Option<&str>,
+        form: Option<&BTreeMap<String, String>>,
+        content_type: Option<&str>,
+    ) -> Result<Self> {
+        let method = match method {
+            Some(m) => Method::from_bytes(m.trim().to_uppercase().as_bytes())
+                .with_context(|| format!("Invalid HTTP method '{}'", m))?,
+            None => Method::GET,
+        };
+        let body = if let Some(path) = body_file {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Could not read body file {}", path))?;
+            Some(Body::Text(text))
+        } else if let Some(fields) = form {
+            Some(Body::Form(fields.iter().map(|(k, v)| (k.clone(), v.clone())).collect()))
+        } else {
+            body.map(|value| match value {
+                serde_json::Value::String(text) => Body::Text(text.clone()),
+                other => Body::Json(other.clone()),
+            })
+        };
+        Ok(RequestSpec { method, body, content_type: content_type.map(str::to_string) })
+    }
+
+    /// Adds the rendered body and Content-Type to a request
+    /// Templates are rendered on every call, so built-ins like `{{uuid}}` differ per check
+    ///
+    /// # Arguments
+    /// * `request` - Request to add the body to
+    /// * `vars` - Template variables
+    ///
+    /// # Returns
+    /// * `Result<RequestBuilder>` - Request with body, or a template error
+    pub fn apply(&self, mut request: RequestBuilder, vars: &HashMap<String, String>) -> Result<RequestBuilder> {
+        // Set before the body: json() keeps an explicit Content-Type, form() always
+        // sends application/x-www-form-urlencoded
+        if let Some(content_type) = &self.content_type {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        request = match &self.body {
+            Some(Body::Json(value)) => request.json(&template::render_json(value, vars)?),
+            Some(Body::Text(text)) => request.body(template::render(text, vars)?),
+            Some(Body::Form(fields)) => {
+                let fields = fields
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), template::render(v, vars)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                request.form(&fields)
+            }
+            None => request,
+        };
+        Ok(request)
+    }
+}
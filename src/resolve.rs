@@ -0,0 +1,272 @@
+// curl-style connection routing: --resolve and --connect-to overrides and per-address fan-out
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Url;
+
+/// A `--resolve host:port:addr[,addr...]` rule
+/// Pins `host` (on `port`, or any port for `*`) to fixed addresses
+#[derive(Debug, Clone)]
+pub struct ResolveRule {
+    host: String,
+    port: Option<u16>,
+    addrs: Vec<IpAddr>,
+}
+
+/// A `--connect-to HOST1:PORT1:HOST2:PORT2` rule
+/// Requests for HOST1:PORT1 connect to HOST2:PORT2 instead; empty fields act as wildcards
+/// on the left side and keep the original value on the right side
+#[derive(Debug, Clone)]
+pub struct ConnectTo {
+    from_host: Option<String>,
+    from_port: Option<u16>,
+    to_host: Option<String>,
+    to_port: Option<u16>,
+}
+
+/// IP address family a check is restricted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    /// Returns the family of an address
+    pub fn of(ip: &IpAddr) -> Self {
+        if ip.is_ipv4() {
+            IpFamily::V4
+        } else {
+            IpFamily::V6
+        }
+    }
+
+    /// Unspecified local address of this family
+    /// Binding to it restricts connections to addresses of the same family
+    pub fn unspecified(self) -> IpAddr {
+        match self {
+            IpFamily::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpFamily::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+
+    /// Label used in reports ("IPv4" / "IPv6")
+    pub fn label(self) -> &'static str {
+        match self {
+            IpFamily::V4 => "IPv4",
+            IpFamily::V6 => "IPv6",
+        }
+    }
+}
+
+/// Where a request for a URL is actually sent
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub request_url: Url,             // URL to request (port may be rewritten by --connect-to)
+    pub host_header: Option<String>,  // Original Host header when the port was rewritten
+    pub addrs: Option<Vec<IpAddr>>,   // Addresses to pin the URL host to, None = normal DNS
+}
+
+impl std::str::FromStr for ResolveRule {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let (host, rest) = split_field(spec).ok_or_else(|| anyhow!("expected host:port:addr"))?;
+        let (port, addrs) = split_field(rest).ok_or_else(|| anyhow!("expected host:port:addr"))?;
+        let port = match port {
+            "*" => None,
+            p => Some(p.parse().with_context(|| format!("invalid port '{}'", p))?),
+        };
+        let addrs = addrs
+            .split(',')
+            .map(|a| {
+                let a = a.trim().trim_start_matches('[').trim_end_matches(']');
+                a.parse::<IpAddr>().with_context(|| format!("invalid address '{}'", a))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if host.is_empty() || addrs.is_empty() {
+            bail!("expected host:port:addr");
+        }
+        Ok(ResolveRule { host: normalize_host(host), port, addrs })
+    }
+}
+
+impl std::str::FromStr for ConnectTo {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let err = || anyhow!("expected HOST1:PORT1:HOST2:PORT2");
+        let (from_host, rest) = split_field(spec).ok_or_else(err)?;
+        let (from_port, rest) = split_field(rest).ok_or_else(err)?;
+        let (to_host, to_port) = split_field(rest).ok_or_else(err)?;
+        let port = |p: &str| -> Result<Option<u16>> {
+            if p.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(p.parse().with_context(|| format!("invalid port '{}'", p))?))
+            }
+        };
+        let host = |h: &str| Some(normalize_host(h)).filter(|h| !h.is_empty());
+        Ok(ConnectTo {
+            from_host: host(from_host),
+            from_port: port(from_port)?,
+            to_host: host(to_host),
+            to_port: port(to_port)?,
+        })
+    }
+}
+
+/// Splits the first `:`-separated field off a rule, treating `[...]` as one IPv6 field
+fn split_field(s: &str) -> Option<(&str, &str)> {
+    if s.starts_with('[') {
+        let end = s.find(']')?;
+        let rest = s[end + 1..].strip_prefix(':')?;
+        Some((&s[..=end], rest))
+    } else {
+        s.split_once(':')
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim_start_matches('[').trim_end_matches(']').to_lowercase()
+}
+
+/// Works out how a URL should be routed given the configured rules
+/// `--connect-to` is applied first; `--resolve` rules then match the resulting host and port
+/// If `--connect-to` points at another host name without a matching `--resolve` rule,
+/// that host is looked up in DNS so the original host name is still used for Host and SNI
+///
+/// # Arguments
+/// * `url` - URL to check
+/// * `resolves` - `--resolve` rules
+/// * `connects` - `--connect-to` rules
+///
+/// # Returns
+/// * `Result<Route>` - Route for the URL or a DNS lookup error
+pub async fn route(url: &Url, resolves: &[ResolveRule], connects: &[ConnectTo]) -> Result<Route> {
+    let host = normalize_host(url.host_str().unwrap_or_default());
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let (connect_host, connect_port) = connects
+        .iter()
+        .find(|c| {
+            c.from_host.as_ref().map_or(true, |h| *h == host) && c.from_port.map_or(true, |p| p == port)
+        })
+        .map(|c| (c.to_host.clone().unwrap_or_else(|| host.clone()), c.to_port.unwrap_or(port)))
+        .unwrap_or_else(|| (host.clone(), port));
+
+    let pinned = resolves
+        .iter()
+        .find(|r| r.host == connect_host && r.port.map_or(true, |p| p == connect_port))
+        .map(|r| r.addrs.clone());
+
+    let addrs = match pinned {
+        Some(addrs) => Some(addrs),
+        None if connect_host != host => Some(lookup(&connect_host, connect_port).await?),
+        None => None,
+    };
+
+    let mut request_url = url.clone();
+    let mut host_header = None;
+    if connect_port != port {
+        host_header = Some(match url.port() {
+            Some(p) => format!("{}:{}", url.host_str().unwrap_or_default(), p),
+            None => url.host_str().unwrap_or_default().to_string(),
+        });
+        request_url
+            .set_port(Some(connect_port))
+            .map_err(|_| anyhow!("cannot change the port of {}", url))?;
+    }
+
+    Ok(Route { request_url, host_header, addrs })
+}
+
+/// Resolves a host name to all of its A/AAAA records
+/// IP literals are returned as-is without a lookup
+pub async fn lookup(host: &str, port: u16) -> Result<Vec<IpAddr>> {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![ip]);
+    }
+    let mut addrs: Vec<IpAddr> = Vec::new();
+    for addr in tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("failed to resolve {}", host))?
+    {
+        // The resolver may return the same address once per socket type
+        if !addrs.contains(&addr.ip()) {
+            addrs.push(addr.ip());
+        }
+    }
+    if addrs.is_empty() {
+        bail!("no addresses found for {}", host);
+    }
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error<T: std::str::FromStr<Err = anyhow::Error>>(spec: &str) -> String {
+        match spec.parse::<T>() {
+            Ok(_) => panic!("'{}' should not parse", spec),
+            Err(e) => format!("{:#}", e),
+        }
+    }
+
+    #[test]
+    fn resolve_rules_parse_ports_and_address_lists() {
+        let rule: ResolveRule = "Example.com:443:127.0.0.1, [::1]".parse().unwrap();
+        assert_eq!(rule.host, "example.com");
+        assert_eq!(rule.port, Some(443));
+        assert_eq!(rule.addrs, ["127.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()]);
+
+        let rule: ResolveRule = "[::1]:*:::2".parse().unwrap();
+        assert_eq!((rule.host.as_str(), rule.port), ("::1", None));
+    }
+
+    #[test]
+    fn bad_resolve_rules_are_rejected() {
+        assert_eq!(error::<ResolveRule>("example.com"), "expected host:port:addr");
+        assert_eq!(error::<ResolveRule>("example.com:443"), "expected host:port:addr");
+        assert_eq!(error::<ResolveRule>(":443:127.0.0.1"), "expected host:port:addr");
+        assert_eq!(error::<ResolveRule>("example.com:https:127.0.0.1"), "invalid port 'https': invalid digit found in string");
+        assert_eq!(error::<ResolveRule>("example.com:70000:127.0.0.1"), "invalid port '70000': number too large to fit in target type");
+        assert_eq!(error::<ResolveRule>("example.com:443:localhost"), "invalid address 'localhost': invalid IP address syntax");
+        assert_eq!(error::<ResolveRule>("example.com:443:"), "invalid address '': invalid IP address syntax");
+    }
+
+    #[test]
+    fn connect_to_fields_may_be_empty() {
+        let rule: ConnectTo = "::backend:8080".parse().unwrap();
+        assert_eq!((rule.from_host, rule.from_port), (None, None));
+        assert_eq!((rule.to_host.as_deref(), rule.to_port), (Some("backend"), Some(8080)));
+
+        let rule: ConnectTo = "[::1]:443:[::2]:".parse().unwrap();
+        assert_eq!((rule.from_host.as_deref(), rule.from_port), (Some("::1"), Some(443)));
+        assert_eq!((rule.to_host.as_deref(), rule.to_port), (Some("::2"), None));
+    }
+
+    #[test]
+    fn bad_connect_to_rules_are_rejected() {
+        assert_eq!(error::<ConnectTo>("a:1:b"), "expected HOST1:PORT1:HOST2:PORT2");
+        assert_eq!(error::<ConnectTo>("[::1:443:b:1"), "expected HOST1:PORT1:HOST2:PORT2");
+        assert_eq!(error::<ConnectTo>("a:x:b:1"), "invalid port 'x': invalid digit found in string");
+        assert_eq!(error::<ConnectTo>("a:1:b:1:2"), "invalid port '1:2': invalid digit found in string");
+    }
+
+    #[tokio::test]
+    async fn connect_to_rewrites_the_port_before_resolve_rules_match() {
+        let url = Url::parse("http://example.com/health").unwrap();
+        let resolves = ["example.com:8080:127.0.0.1".parse().unwrap()];
+        let connects = [":80::8080".parse().unwrap()];
+        let routed = route(&url, &resolves, &connects).await.unwrap();
+        assert_eq!(routed.request_url.as_str(), "http://example.com:8080/health");
+        assert_eq!(routed.host_header.as_deref(), Some("example.com"));
+        assert_eq!(routed.addrs, Some(vec!["127.0.0.1".parse().unwrap()]));
+
+        // Without a matching rule the URL is left to normal DNS
+        let routed = route(&url, &resolves, &[]).await.unwrap();
+        assert_eq!((routed.request_url, routed.host_header, routed.addrs), (url, None, None));
+    }
+}
@@ -0,0 +1,645 @@
+// Multi-step synthetic transactions: ordered requests with assertions and extracted variables
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+
+use crate::category::{self, Category};
+use crate::proxy::NoProxy;
+use crate::request::RequestSpec;
+use crate::resolve::{self, IpFamily};
+use crate::session::Session;
+use crate::{client_for, template, Args, ClientKey, ResultRow};
+
+/// A scenario: ordered steps sharing one cookie jar and a set of variables
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    pub name: String,                       // Scenario name used in the report
+    #[serde(default)]
+    pub variables: IndexMap<String, String>, // Initial variables, templated in the order written
+    pub steps: Vec<Step>,                   // Steps, executed in order until one fails
+    #[serde(default)]
+    pub tags: Vec<String>,                  // Labels copied to every row of the scenario
+}
+
+/// A single request of a scenario
+#[derive(Debug, Clone, Deserialize)]
+pub struct Step {
+    #[serde(default)]
+    pub name: Option<String>,                   // Step name, "step N" if absent
+    pub url: String,                            // URL (templated)
+    #[serde(default)]
+    pub method: Option<String>,                 // HTTP method, GET if absent
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,      // Extra request headers (values templated)
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,        // Inline body, as for structured input
+    #[serde(default)]
+    pub body_file: Option<String>,              // Body file, as for structured input
+    #[serde(default)]
+    pub form: Option<BTreeMap<String, String>>, // Form fields, as for structured input
+    #[serde(default)]
+    pub content_type: Option<String>,           // Content-Type override
+    #[serde(default)]
+    pub expect: Expect,                         // Assertions on the response
+    #[serde(default)]
+    pub extract: BTreeMap<String, Extract>,     // Variables to capture for later steps
+}
+
+/// Assertions on a step's response
+/// Without a `status` assertion any 2xx/3xx status passes
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Expect {
+    #[serde(default)]
+    pub status: Option<OneOrMany<u16>>,             // Accepted status code(s)
+    #[serde(default)]
+    pub body_contains: Option<OneOrMany<String>>,   // Text(s) the body must contain (templated)
+    #[serde(default)]
+    pub json: BTreeMap<String, serde_json::Value>,  // JSON pointer -> expected value
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,          // Header -> text its value must contain
+    #[serde(default)]
+    pub max_time_ms: Option<u128>,                  // Maximum step duration
+}
+
+/// Where to take an extracted variable from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Extract {
+    Json(String),   // JSON pointer into the response body, e.g. "/data/id"
+    Header(String), // Response header name
+}
+
+/// A single value or a list of values
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: Clone> OneOrMany<T> {
+    fn to_vec(&self) -> Vec<T> {
+        match self {
+            OneOrMany::One(v) => vec![v.clone()],
+            OneOrMany::Many(v) => v.clone(),
+        }
+    }
+}
+
+/// Loads scenarios from a JSON file holding one scenario object or an array of them
+pub fn load(path: &str) -> Result<Vec<Scenario>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum File {
+        One(Scenario),
+        Many(Vec<Scenario>),
+    }
+
+    let data = std::fs::read_to_string(path).with_context(|| format!("Failed to read scenario file {}", path))?;
+    let file: File = serde_json::from_str(&data).with_context(|| format!("Invalid scenario file {}", path))?;
+    Ok(match file {
+        File::One(s) => vec![s],
+        File::Many(s) => s,
+    })
+}
+
+/// Routes a scenario's steps like single checks: the NO_PROXY bypass, `--resolve`,
+/// `--connect-to` and the address family apply per step, since step URLs are templated
+/// Every client shares the scenario's cookie jar
+pub struct Router<'a> {
+    args: &'a Args,                      // Command-line arguments with routing and HTTP settings
+    session: Session,                    // The scenario's own cookie jar and the login token
+    proxy: Option<Url>,                  // Proxy from --proxy, None = direct
+    no_proxy: NoProxy,                   // Hosts that bypass the proxy
+    family: Option<IpFamily>,            // Address family to connect over, None = any
+    clients: HashMap<ClientKey, Client>, // One client per distinct route
+}
+
+impl<'a> Router<'a> {
+    /// Creates the router of one scenario run
+    pub fn new(args: &'a Args, session: Session, proxy: Option<Url>, no_proxy: NoProxy, family: Option<IpFamily>) -> Self {
+        Router { args, session, proxy, no_proxy, family, clients: HashMap::new() }
+    }
+
+    /// Picks the client for a step URL
+    ///
+    /// # Arguments
+    /// * `parsed` - Rendered step URL
+    ///
+    /// # Returns
+    /// * `Result<(Client, String, Option<String>)>` - Client, URL to request and Host header
+    ///   override (set when `--connect-to` changed the port), or a lookup error
+    async fn route(&mut self, parsed: &Url) -> Result<(Client, String, Option<String>)> {
+        let proxy = if self.no_proxy.matches(parsed) { None } else { self.proxy.clone() };
+        let route = resolve::route(parsed, &self.args.resolve, &self.args.connect_to).await?;
+        let host = parsed.host_str().unwrap_or_default().to_string();
+        let port = route.request_url.port_or_known_default().unwrap_or(80);
+        let pin = route
+            .addrs
+            .map(|addrs| (host, addrs.iter().map(|ip| SocketAddr::new(*ip, port)).collect::<Vec<_>>()));
+        let key = ClientKey { proxy, pin, family: self.family, connect_timeout: self.args.connect_timeout };
+        let client = client_for(&mut self.clients, self.args, &self.session, key)?;
+        Ok((client, route.request_url.to_string(), route.host_header))
+    }
+}
+
+/// Runs a scenario's steps in order, stopping at the first failing step
+/// A step cut off by the run deadline is reported as SKIPPED, and so is the scenario
+///
+/// # Arguments
+/// * `scenario` - Scenario to run
+/// * `router` - Clients for the steps, sharing one cookie jar so the steps share one session
+/// * `timeout` - Total timeout per step
+/// * `deadline` - Point in time after which the scenario is cut off, if any
+/// * `vars` - Global template variables (from `--var`)
+///
+/// # Returns
+/// * `Vec<ResultRow>` - One row per executed step followed by the scenario's PASS/FAIL/SKIPPED row
+pub async fn run(
+    scenario: Scenario,
+    mut router: Router<'_>,
+    timeout: Duration,
+    deadline: Option<tokio::time::Instant>,
+    vars: &HashMap<String, String>,
+) -> Vec<ResultRow> {
+    let started = Instant::now();
+    let mut rows = Vec::new();
+    let mut failure = None;
+    let mut skipped = false;
+
+    // Scenario variables may refer to global ones, the environment and variables written before them
+    let mut vars = vars.clone();
+    for (name, value) in &scenario.variables {
+        match template::render(value, &vars) {
+            Ok(value) => {
+                vars.insert(name.clone(), value);
+            }
+            Err(e) => {
+                failure = Some(format!("variable '{}': {:#}", name, e));
+                break;
+            }
+        }
+    }
+
+    if failure.is_none() && deadline.is_some_and(|d| tokio::time::Instant::now() >= d) {
+        failure = Some("not started: run deadline reached".to_string());
+        skipped = true;
+    }
+
+    if failure.is_none() {
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let step_name = step.name.clone().unwrap_or_else(|| format!("step {}", index + 1));
+            let attempt = run_step(&scenario.name, &step_name, step, &mut router, timeout, &mut vars);
+            let mut row = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, attempt).await {
+                    Ok(row) => row,
+                    Err(_) => ResultRow {
+                        url: step.url.clone(),
+                        status: "SKIPPED".to_string(),
+                        reason: "cancelled: run deadline reached".to_string(),
+                        timestamp: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                        scenario: scenario.name.clone(),
+                        step: step_name.clone(),
+                        ..Default::default()
+                    },
+                },
+                None => attempt.await,
+            };
+            row.tags = scenario.tags.join(",");
+            let failed = !row.step_passed();
+            if failed {
+                skipped = row.status == "SKIPPED";
+                let why = if row.assertions.is_empty() { &row.reason } else { &row.assertions };
+                failure = Some(format!("{} failed: {}", step_name, why));
+            }
+            rows.push(row);
+            if failed {
+                break;
+            }
+        }
+    }
+
+    let status = match (&failure, skipped) {
+        (None, _) => "PASS",
+        (Some(_), true) => "SKIPPED",
+        (Some(_), false) => "FAIL",
+    };
+    rows.push(ResultRow {
+        url: format!("scenario:{}", scenario.name),
+        status: status.to_string(),
+        reason: failure.unwrap_or_else(|| format!("{} step(s) passed", scenario.steps.len())),
+        time_ms: started.elapsed().as_millis(),
+        size_bytes: rows.iter().map(|r| r.size_bytes).sum(),
+        timestamp: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        scenario: scenario.name,
+        tags: scenario.tags.join(","),
+        ..Default::default()
+    });
+    rows
+}
+
+/// Executes one step: renders the request, sends it, checks assertions and extracts variables
+async fn run_step(
+    scenario: &str,
+    step_name: &str,
+    step: &Step,
+    router: &mut Router<'_>,
+    timeout: Duration,
+    vars: &mut HashMap<String, String>,
+) -> ResultRow {
+    let mut row = ResultRow {
+        url: step.url.clone(),
+        scenario: scenario.to_string(),
+        step: step_name.to_string(),
+        timestamp: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        ..Default::default()
+    };
+    let fail = |mut row: ResultRow, reason: String, category: Category| {
+        row.status = "ERROR".to_string();
+        row.reason = reason;
+        row.category = Some(category);
+        row
+    };
+
+    match template::render(&step.url, vars) {
+        Ok(url) => row.url = url,
+        Err(e) => return fail(row, format!("{:#}", e), Category::Other),
+    }
+    let url = match Url::parse(&row.url) {
+        Ok(url) => url,
+        Err(e) => return fail(row, format!("invalid URL: {}", e), Category::Other),
+    };
+    // Routing only fails when the host a --connect-to rule points at cannot be resolved
+    let (client, request_url, host_header) = match router.route(&url).await {
+        Ok(route) => route,
+        Err(e) => return fail(row, format!("{:#}", e), Category::Dns),
+    };
+    let request = match build_request(step, &client, &request_url, host_header, timeout, vars) {
+        Ok(request) => request,
+        Err(e) => return fail(row, format!("{:#}", e), Category::Other),
+    };
+
+    // Step time covers the whole exchange including the body, which assertions need
+    let start = Instant::now();
+    let resp = match request.send().await {
+        Ok(resp) => resp,
+        Err(e) => return fail(row, format!("{}", e), category::of_error(&e)),
+    };
+    row.status = resp.status().as_u16().to_string();
+    row.reason = resp.status().canonical_reason().unwrap_or("").to_string();
+    row.remote_addr = resp.remote_addr().map(|a| a.to_string()).unwrap_or_default();
+    row.ip_family = resp
+        .remote_addr()
+        .map(|a| IpFamily::of(&a.ip()).label().to_string())
+        .unwrap_or_default();
+    row.http_version = format!("{:?}", resp.version());
+    let status = resp.status().as_u16();
+    let headers = resp.headers().clone();
+    let body = match resp.text().await {
+        Ok(body) => body,
+        Err(e) => return fail(row, format!("error reading body: {}", e), category::of_error(&e)),
+    };
+    row.time_ms = start.elapsed().as_millis();
+    row.size_bytes = body.len() as u64;
+
+    let json = serde_json::from_str::<serde_json::Value>(&body).ok();
+    let mut failures = check_expectations(&step.expect, status, &headers, &body, json.as_ref(), row.time_ms, vars);
+
+    for (name, source) in &step.extract {
+        let value = match source {
+            Extract::Json(pointer) => json.as_ref().and_then(|j| j.pointer(pointer)).map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }),
+            Extract::Header(header) => headers
+                .get(header.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+        };
+        match value {
+            Some(value) => {
+                vars.insert(name.clone(), value);
+            }
+            None => failures.push(format!("could not extract '{}' from {:?}", name, source)),
+        }
+    }
+
+    row.assertions = failures.join("; ");
+    row
+}
+
+/// Renders a step's headers and body into a request for the routed URL
+fn build_request(
+    step: &Step,
+    client: &Client,
+    url: &str,
+    host_header: Option<String>,
+    timeout: Duration,
+    vars: &HashMap<String, String>,
+) -> Result<reqwest::RequestBuilder> {
+    let spec = RequestSpec::new(
+        step.method.as_deref(),
+        step.body.as_ref(),
+        step.body_file.as_deref(),
+        step.form.as_ref(),
+        step.content_type.as_deref(),
+    )?;
+    let mut request = client.request(spec.method.clone(), url).timeout(timeout);
+    if let Some(host) = host_header {
+        request = request.header(reqwest::header::HOST, host);
+    }
+    for (name, value) in &step.headers {
+        request = request.header(name.as_str(), template::render(value, vars)?);
+    }
+    spec.apply(request, vars)
+}
+
+/// Evaluates a step's assertions and returns a message for every failed one
+fn check_expectations(
+    expect: &Expect,
+    status: u16,
+    headers: &reqwest::header::HeaderMap,
+    body: &str,
+    json: Option<&serde_json::Value>,
+    time_ms: u128,
+    vars: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    match &expect.status {
+        Some(accepted) if !accepted.to_vec().contains(&status) => {
+            failures.push(format!("expected status {:?}, got {}", accepted.to_vec(), status));
+        }
+        None if !(200..400).contains(&status) => {
+            failures.push(format!("expected a 2xx/3xx status, got {}", status));
+        }
+        _ => {}
+    }
+
+    for needle in expect.body_contains.iter().flat_map(|n| n.to_vec()) {
+        let needle = template::render(&needle, vars).unwrap_or(needle);
+        if !body.contains(&needle) {
+            failures.push(format!("expected body to contain '{}'", needle));
+        }
+    }
+
+    for (pointer, expected) in &expect.json {
+        match json.and_then(|j| j.pointer(pointer)) {
+            Some(actual) if actual == expected => {}
+            Some(actual) => failures.push(format!("expected {} to be {}, got {}", pointer, expected, actual)),
+            None => failures.push(format!("expected {} in JSON body", pointer)),
+        }
+    }
+
+    for (name, expected) in &expect.headers {
+        let actual = headers.get(name.as_str()).and_then(|v| v.to_str().ok());
+        if !actual.is_some_and(|v| v.contains(expected.as_str())) {
+            failures.push(format!("expected header {} to contain '{}'", name, expected));
+        }
+    }
+
+    if let Some(max) = expect.max_time_ms {
+        if time_ms > max {
+            failures.push(format!("expected at most {} ms, took {} ms", max, time_ms));
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use clap::Parser;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Request heads received by a stub server, lowercased
+    type Requests = Arc<Mutex<Vec<String>>>;
+
+    /// Starts a stub HTTP server answering every request with `respond(head)`
+    async fn stub(respond: fn(&str) -> String) -> (u16, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Requests::default();
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut head = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => head.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&head).to_lowercase();
+                let response = respond(&head);
+                seen.lock().unwrap().push(head);
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        (port, requests)
+    }
+
+    /// A complete HTTP/1.1 response
+    fn response(status: &str, headers: &[&str], body: &str) -> String {
+        let mut text = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+        for header in headers {
+            text.push_str(header);
+            text.push_str("\r\n");
+        }
+        format!("{}\r\n{}", text, body)
+    }
+
+    /// Stub shop: a login handing out a token and a session header, an API that needs both,
+    /// and 404 for everything else
+    fn shop(head: &str) -> String {
+        let path = head.split_whitespace().nth(1).unwrap_or("");
+        match path {
+            "/login" => response(
+                "200 OK",
+                &["Content-Type: application/json", "X-Session: s-42"],
+                r#"{"token":"abc","user":{"id":7}}"#,
+            ),
+            "/orders?session=s-42" if head.contains("authorization: bearer abc") => {
+                response("200 OK", &["Content-Type: application/json"], r#"{"status":"ok","count":3}"#)
+            }
+            "/orders?session=s-42" => response("401 Unauthorized", &[], "no token"),
+            _ => response("404 Not Found", &[], "not here"),
+        }
+    }
+
+    async fn run_json(args: &Args, scenario: serde_json::Value, deadline: Option<tokio::time::Instant>) -> Vec<ResultRow> {
+        run_scenario(args, serde_json::from_value(scenario).unwrap(), deadline).await
+    }
+
+    /// Runs a scenario with its own cookie jar and no global variables
+    async fn run_scenario(args: &Args, scenario: Scenario, deadline: Option<tokio::time::Instant>) -> Vec<ResultRow> {
+        let session = Session { jar: Some(Arc::new(crate::session::CookieJar::new())), token: None };
+        let router = Router::new(args, session, None, NoProxy::default(), None);
+        run(scenario, router, Duration::from_secs(5), deadline, &HashMap::new()).await
+    }
+
+    fn args(extra: &[&str]) -> Args {
+        Args::parse_from(std::iter::once("url-checker").chain(extra.iter().copied()))
+    }
+
+    #[tokio::test]
+    async fn expected_error_status_passes() {
+        let (port, _) = stub(shop).await;
+        let rows = run_json(
+            &args(&[]),
+            json!({ "name": "gone", "steps": [
+                { "url": format!("http://127.0.0.1:{}/deleted", port), "expect": { "status": 404 } },
+                { "url": format!("http://127.0.0.1:{}/login", port) }
+            ]}),
+            None,
+        )
+        .await;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].status, "404");
+        assert!(rows[0].is_up(), "a 404 the step expects counts as up");
+        assert_eq!(rows[2].status, "PASS");
+    }
+
+    #[tokio::test]
+    async fn unexpected_status_fails_and_stops() {
+        let (port, requests) = stub(shop).await;
+        let rows = run_json(
+            &args(&[]),
+            json!({ "name": "broken", "steps": [
+                { "name": "open", "url": format!("http://127.0.0.1:{}/missing", port) },
+                { "url": format!("http://127.0.0.1:{}/login", port) }
+            ]}),
+            None,
+        )
+        .await;
+        assert_eq!(rows.len(), 2, "the second step must not run");
+        assert!(!rows[0].is_up());
+        assert_eq!(rows[0].assertions, "expected a 2xx/3xx status, got 404");
+        assert_eq!(rows[1].status, "FAIL");
+        assert!(rows[1].reason.starts_with("open failed:"));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // The scenario is one failed check; its step only feeds the response figures
+        let stats = crate::Stats::from_rows(&rows);
+        assert_eq!((stats.total, stats.up, stats.down), (1, 0, 1));
+        assert_eq!(stats.latency.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn assertions_report_every_failure() {
+        let (port, _) = stub(shop).await;
+        let rows = run_json(
+            &args(&[]),
+            json!({ "name": "asserts", "steps": [
+                { "url": format!("http://127.0.0.1:{}/login", port), "expect": {
+                    "status": [200, 201],
+                    "body_contains": "token",
+                    "json": { "/user/id": 7 },
+                    "headers": { "x-session": "s-" },
+                    "max_time_ms": 5000
+                }},
+                { "url": format!("http://127.0.0.1:{}/login", port), "expect": {
+                    "status": 201,
+                    "body_contains": ["token", "secret"],
+                    "json": { "/user/id": 8, "/missing": true },
+                    "headers": { "x-session": "t-" }
+                }}
+            ]}),
+            None,
+        )
+        .await;
+        assert!(rows[0].step_passed(), "unexpected failures: {}", rows[0].assertions);
+        let failures = rows[1].assertions.split("; ").collect::<Vec<_>>();
+        assert_eq!(
+            failures,
+            [
+                "expected status [201], got 200",
+                "expected body to contain 'secret'",
+                "expected /missing in JSON body",
+                "expected /user/id to be 8, got 7",
+                "expected header x-session to contain 't-'",
+            ]
+        );
+        assert_eq!(rows[2].status, "FAIL");
+    }
+
+    #[tokio::test]
+    async fn extracted_variables_reach_later_steps() {
+        let (port, requests) = stub(shop).await;
+        let rows = run_json(
+            &args(&[]),
+            json!({ "name": "orders", "variables": { "base": format!("http://127.0.0.1:{}", port) }, "steps": [
+                { "url": "{{base}}/login", "method": "POST",
+                  "extract": { "token": { "json": "/token" }, "session": { "header": "X-Session" } } },
+                { "url": "{{base}}/orders?session={{session}}",
+                  "headers": { "Authorization": "Bearer {{token}}" },
+                  "expect": { "json": { "/status": "ok" } } },
+                { "url": "{{base}}/login", "extract": { "nothing": { "json": "/nope" } } }
+            ]}),
+            None,
+        )
+        .await;
+        assert_eq!(rows[1].status, "200", "{:?}", rows[1]);
+        assert_eq!(rows[1].url, format!("http://127.0.0.1:{}/orders?session=s-42", port));
+        assert!(requests.lock().unwrap()[1].contains("authorization: bearer abc"));
+        assert_eq!(rows[2].assertions, r#"could not extract 'nothing' from Json("/nope")"#);
+        assert_eq!(rows[3].status, "FAIL");
+    }
+
+    #[tokio::test]
+    async fn variables_refer_to_those_written_before_them() {
+        let (port, _) = stub(shop).await;
+        // Out of alphabetical order, so a sorted or hashed map would render "login" first
+        let scenario = format!(
+            r#"{{ "name": "chained", "variables": {{ "root": "http://127.0.0.1:{}", "login": "{{{{root}}}}/login" }},
+                 "steps": [{{ "url": "{{{{login}}}}" }}] }}"#,
+            port
+        );
+        let scenario: Scenario = serde_json::from_str(&scenario).unwrap();
+        assert_eq!(scenario.variables.keys().collect::<Vec<_>>(), ["root", "login"]);
+        let rows = run_scenario(&args(&[]), scenario, None).await;
+        assert_eq!(rows[0].url, format!("http://127.0.0.1:{}/login", port));
+        assert_eq!(rows[1].status, "PASS", "{:?}", rows[1]);
+    }
+
+    #[tokio::test]
+    async fn steps_follow_connect_to() {
+        let (port, requests) = stub(shop).await;
+        let rule = format!("shop.test:80:127.0.0.1:{}", port);
+        let rows = run_json(
+            &args(&["--connect-to", &rule]),
+            json!({ "name": "routed", "steps": [{ "url": "http://shop.test/login" }] }),
+            None,
+        )
+        .await;
+        assert_eq!(rows[0].status, "200", "{:?}", rows[0]);
+        assert_eq!(rows[0].url, "http://shop.test/login");
+        assert!(requests.lock().unwrap()[0].contains("host: shop.test\r\n"));
+    }
+
+    #[tokio::test]
+    async fn passed_deadline_skips_scenario() {
+        let rows = run_json(
+            &args(&[]),
+            json!({ "name": "late", "steps": [{ "url": "http://127.0.0.1:1/" }] }),
+            Some(tokio::time::Instant::now()),
+        )
+        .await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].status, "SKIPPED");
+        assert_eq!(rows[0].reason, "not started: run deadline reached");
+    }
+}
@@ -0,0 +1,140 @@
+// Shared session state: a persistent cookie jar and an optional login step run before the checks
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::HeaderValue;
+use reqwest::{Client, Url};
+
+/// State shared by every client of a run
+#[derive(Default)]
+pub struct Session {
+    pub jar: Option<Arc<CookieJar>>,    // Shared cookie jar, None = cookies are not kept
+    pub token: Option<String>,          // Bearer token obtained by the login step
+}
+
+/// Cookie jar shared by every client of a run
+/// Unlike `reqwest::cookie::Jar` it can be saved to and loaded from a file
+pub struct CookieJar(RwLock<cookie_store::CookieStore>);
+
+impl CookieJar {
+    /// Loads a jar from a file written by `save`, or starts empty if the file does not exist
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Ok(Self::new());
+        }
+        let file = File::open(path).with_context(|| format!("Could not open cookie jar {}", path))?;
+        let store = cookie_store::CookieStore::load_json_all(BufReader::new(file))
+            .map_err(|e| anyhow!("Invalid cookie jar {}: {}", path, e))?;
+        Ok(CookieJar(RwLock::new(store)))
+    }
+
+    /// Creates an empty in-memory jar
+    pub fn new() -> Self {
+        CookieJar(RwLock::new(cookie_store::CookieStore::default()))
+    }
+
+    /// Writes all unexpired cookies, including session cookies, one JSON object per line
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("Could not create cookie jar {}", path))?;
+        let mut writer = BufWriter::new(file);
+        let store = self.0.read().unwrap();
+        for cookie in store.iter_unexpired() {
+            writeln!(writer, "{}", serde_json::to_string(cookie)?)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| cookie::Cookie::parse(value.to_string()).ok());
+        self.0.write().unwrap().store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .0
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&header).ok()
+        }
+    }
+}
+
+/// Credentials to submit before the checks run
+pub enum LoginBody {
+    Form(Vec<(String, String)>),    // application/x-www-form-urlencoded fields
+    Json(serde_json::Value),        // application/json document
+}
+
+/// Parses a `key=value` pair (login form fields, template variables)
+pub fn parse_key_value(s: &str) -> Result<(String, String)> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| anyhow!("expected key=value, got '{}'", s))
+}
+
+/// Parses a JSON document given inline or as `@path/to/file.json`
+pub fn parse_json_arg(s: &str) -> Result<serde_json::Value> {
+    let text = match s.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path))?,
+        None => s.to_string(),
+    };
+    serde_json::from_str(&text).context("Invalid JSON")
+}
+
+/// Performs the login request; cookies it sets end up in the client's jar
+///
+/// # Arguments
+/// * `client` - Client sharing the run's cookie jar
+/// * `url` - Login URL to POST the credentials to
+/// * `body` - Form fields or JSON credentials
+/// * `token_field` - Field of a JSON response holding a bearer token, as a top-level
+///   name or a JSON pointer such as `/data/access_token`
+///
+/// # Returns
+/// * `Result<Option<String>>` - The extracted token, if requested, or an error if the login failed
+pub async fn login(
+    client: &Client,
+    url: &str,
+    body: &LoginBody,
+    token_field: Option<&str>,
+) -> Result<Option<String>> {
+    let request = match body {
+        LoginBody::Form(fields) => client.post(url).form(fields),
+        LoginBody::Json(value) => client.post(url).json(value),
+    };
+    let resp = request.send().await.with_context(|| format!("Login request to {} failed", url))?;
+    let status = resp.status();
+    if !(status.is_success() || status.is_redirection()) {
+        bail!("Login to {} failed with status {}", url, status);
+    }
+
+    let Some(field) = token_field else {
+        return Ok(None);
+    };
+    let json: serde_json::Value = resp.json().await.context("Login response is not JSON")?;
+    let token = if field.starts_with('/') {
+        json.pointer(field)
+    } else {
+        json.get(field)
+    };
+    match token {
+        Some(serde_json::Value::String(token)) => Ok(Some(token.clone())),
+        Some(other) => Ok(Some(other.to_string())),
+        None => bail!("Login response has no '{}' field", field),
+    }
+}
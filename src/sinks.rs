@@ -0,0 +1,482 @@
+// Metric sinks: every check emitted as InfluxDB line protocol, StatsD timers and counters,
+// or Graphite plaintext, for setups that do not scrape Prometheus
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+
+use crate::{category, output, parse_timestamp, ResultRow};
+
+/// Default prefix of StatsD and Graphite metric names, and the InfluxDB measurement
+pub const DEFAULT_PREFIX: &str = "url_checker";
+
+/// Largest StatsD datagram, small enough to avoid IP fragmentation on common networks
+const MAX_DATAGRAM: usize = 1432;
+
+/// Time limit for connecting to and writing to a sink
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Protocol a sink speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkKind {
+    Influx,   // InfluxDB line protocol, appended to a file or POSTed to a write endpoint
+    Statsd,   // StatsD timers, counters and gauges over UDP
+    Graphite, // Graphite plaintext protocol over TCP
+}
+
+impl SinkKind {
+    /// Name of the kind as written in a sink spec
+    pub fn name(&self) -> &'static str {
+        match self {
+            SinkKind::Influx => "influx",
+            SinkKind::Statsd => "statsd",
+            SinkKind::Graphite => "graphite",
+        }
+    }
+}
+
+/// A metric sink, given as `KIND=TARGET` on the command line or as an object in the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SinkSpec {
+    #[serde(rename = "type")]
+    pub kind: SinkKind,                 // Protocol
+    pub target: String,                 // File ("-" for stdout) or http(s) write URL for influx, HOST:PORT otherwise
+    #[serde(default)]
+    pub prefix: Option<String>,         // Metric prefix or measurement, --metric-prefix if absent
+    #[serde(default)]
+    pub token: Option<String>,          // InfluxDB API token, $INFLUX_TOKEN if absent
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>, // Extra InfluxDB tags added to every point
+}
+
+impl FromStr for SinkSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, target) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected KIND=TARGET, got '{}'", s))?;
+        let kind = match kind {
+            "influx" | "influxdb" => SinkKind::Influx,
+            "statsd" => SinkKind::Statsd,
+            "graphite" => SinkKind::Graphite,
+            _ => bail!("unknown sink '{}' (expected influx, statsd or graphite)", kind),
+        };
+        let spec = SinkSpec { kind, target: target.to_string(), prefix: None, token: None, tags: BTreeMap::new() };
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+impl SinkSpec {
+    /// Checks that the target suits the kind and that options apply to it
+    pub fn validate(&self) -> Result<()> {
+        if self.target.is_empty() {
+            bail!("{} sink has no target", self.kind.name());
+        }
+        match self.kind {
+            SinkKind::Influx => {
+                if self.is_http() {
+                    reqwest::Url::parse(&self.target)
+                        .map_err(|e| anyhow!("invalid InfluxDB write URL '{}': {}", self.target, e))?;
+                }
+            }
+            SinkKind::Statsd | SinkKind::Graphite => {
+                let port = self.target.rsplit_once(':').and_then(|(_, port)| port.parse::<u16>().ok());
+                if port.is_none() {
+                    bail!("{} sink needs HOST:PORT, got '{}'", self.kind.name(), self.target);
+                }
+                if self.token.is_some() || !self.tags.is_empty() {
+                    bail!("token and tags only apply to influx sinks");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether an influx target is a write endpoint rather than a file
+    fn is_http(&self) -> bool {
+        self.target.starts_with("http://") || self.target.starts_with("https://")
+    }
+}
+
+/// The run's metric sinks
+pub struct Sinks {
+    sinks: Vec<SinkSpec>, // Sinks to emit to
+    prefix: String,       // Prefix for sinks that do not set their own
+    client: Client,       // Client for InfluxDB write endpoints
+}
+
+impl Sinks {
+    /// Sets up the sinks
+    ///
+    /// # Arguments
+    /// * `sinks` - Sinks from the command line and the config file
+    /// * `prefix` - Default metric prefix
+    ///
+    /// # Returns
+    /// * `Result<Sinks>` - Sinks, or an error building the HTTP client
+    pub fn new(sinks: Vec<SinkSpec>, prefix: &str) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent("url-checker/0.2")
+            .timeout(SEND_TIMEOUT)
+            .build()?;
+        Ok(Sinks { sinks, prefix: prefix.to_string(), client })
+    }
+
+    /// Whether no sink is configured
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Sinks for display, e.g. "statsd 127.0.0.1:8125, influx metrics.lp"
+    pub fn describe(&self) -> String {
+        self.sinks
+            .iter()
+            .map(|s| format!("{} {}", s.kind.name(), s.target))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Emits result rows to every sink concurrently
+    ///
+    /// # Arguments
+    /// * `rows` - Results to emit
+    ///
+    /// # Returns
+    /// * `Vec<String>` - One message per sink that could not be written to
+    pub async fn emit(&self, rows: &[ResultRow]) -> Vec<String> {
+        let sends = self.sinks.iter().map(|sink| async move {
+            self.send(sink, rows)
+                .await
+                .err()
+                .map(|e| format!("{} sink {} failed: {:#}", sink.kind.name(), sink.target, e))
+        });
+        futures::future::join_all(sends).await.into_iter().flatten().collect()
+    }
+
+    /// Encodes the rows for one sink and delivers them
+    async fn send(&self, sink: &SinkSpec, rows: &[ResultRow]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let prefix = sink.prefix.as_deref().unwrap_or(&self.prefix);
+        match sink.kind {
+            SinkKind::Influx => {
+                let body: String = rows.iter().map(|row| influx_line(row, prefix, &sink.tags) + "\n").collect();
+                if sink.is_http() {
+                    self.post_influx(sink, body).await
+                } else {
+                    output::append(&sink.target)?.write_all(body.as_bytes()).map_err(Into::into)
+                }
+            }
+            SinkKind::Statsd => {
+                let lines: Vec<String> = rows.iter().flat_map(|row| statsd_lines(row, prefix)).collect();
+                let addr = resolve(&sink.target).await?;
+                let local: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+                let socket = UdpSocket::bind(local).await?;
+                for datagram in datagrams(&lines) {
+                    socket.send_to(datagram.as_bytes(), addr).await?;
+                }
+                Ok(())
+            }
+            SinkKind::Graphite => {
+                let body: String = rows.iter().flat_map(|row| graphite_lines(row, prefix)).map(|l| l + "\n").collect();
+                let addr = resolve(&sink.target).await?;
+                let write = async {
+                    let mut stream = TcpStream::connect(addr).await?;
+                    stream.write_all(body.as_bytes()).await?;
+                    stream.shutdown().await
+                };
+                tokio::time::timeout(SEND_TIMEOUT, write)
+                    .await
+                    .map_err(|_| anyhow!("timed out after {}s", SEND_TIMEOUT.as_secs()))?
+                    .map_err(Into::into)
+            }
+        }
+    }
+
+    /// POSTs line protocol to an InfluxDB write endpoint (v1 /write or v2 /api/v2/write)
+    async fn post_influx(&self, sink: &SinkSpec, body: String) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&sink.target)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(body);
+        if let Some(token) = sink.token.clone().or_else(|| std::env::var("INFLUX_TOKEN").ok()) {
+            request = request.header(reqwest::header::AUTHORIZATION, format!("Token {}", token));
+        }
+        let resp = request.send().await?;
+        if resp.status().is_success() {
+            return Ok(());
+        }
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        bail!("status {}: {}", status, text.chars().take(200).collect::<String>().trim())
+    }
+}
+
+/// Resolves a HOST:PORT target to its first address
+async fn resolve(target: &str) -> Result<SocketAddr> {
+    tokio::net::lookup_host(target)
+        .await
+        .with_context(|| format!("Could not resolve {}", target))?
+        .next()
+        .ok_or_else(|| anyhow!("{} has no address", target))
+}
+
+/// Encodes a row as one InfluxDB line protocol point
+/// Tags: url, host, status, category (failures), scenario, step, tags and the sink's extra tags;
+/// fields: up, status_code, response_time_ms (responses only), size_bytes, cert_expiry and reason
+fn influx_line(row: &ResultRow, measurement: &str, extra: &BTreeMap<String, String>) -> String {
+    let host = reqwest::Url::parse(&row.url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let mut tags: BTreeMap<&str, String> = extra.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+    tags.insert("url", row.url.clone());
+    tags.insert("host", host);
+    tags.insert("status", row.status.clone());
+    tags.insert("category", category::of(row).map(|c| c.name().to_string()).unwrap_or_default());
+    tags.insert("scenario", row.scenario.clone());
+    tags.insert("step", row.step.clone());
+    tags.insert("tags", row.tags.clone());
+
+    let mut line = escape_influx(measurement, &[',', ' ']);
+    // Empty tag values are not allowed in line protocol
+    for (key, value) in tags.iter().filter(|(_, v)| !v.is_empty()) {
+        let special = [',', '=', ' '];
+        line.push_str(&format!(",{}={}", escape_influx(key, &special), escape_influx(value, &special)));
+    }
+
+    let mut fields = vec![
+        format!("up={}", row.is_up()),
+        format!("status_code={}i", row.status.parse::<u16>().unwrap_or(0)),
+    ];
+    if row.is_timed() {
+        fields.push(format!("response_time_ms={}i", row.time_ms));
+    }
+    fields.push(format!("size_bytes={}i", row.size_bytes));
+    if let Some(expires) = parse_timestamp(&row.cert_expires) {
+        fields.push(format!("cert_expiry={}i", expires.timestamp()));
+    }
+    fields.push(format!("reason=\"{}\"", escape_influx(&row.reason, &['"'])));
+    line.push(' ');
+    line.push_str(&fields.join(","));
+
+    if let Some(time) = parse_timestamp(&row.timestamp) {
+        line.push_str(&format!(" {}", time.timestamp() as i128 * 1_000_000_000));
+    }
+    line
+}
+
+/// Escapes backslashes and the given characters with a backslash; line breaks become spaces
+fn escape_influx(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' | '\r' => escaped.push(' '),
+            '\\' => escaped.push_str("\\\\"),
+            c if special.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Encodes a row as StatsD metrics under `PREFIX.KEY`: a `checks` counter, an `up` gauge,
+/// a `response_time` timer (responses only), a `status.CODE` counter and an
+/// `errors.CATEGORY` counter for failures
+fn statsd_lines(row: &ResultRow, prefix: &str) -> Vec<String> {
+    let base = format!("{}.{}", prefix, metric_key(row));
+    let mut lines = vec![format!("{}.checks:1|c", base), format!("{}.up:{}|g", base, row.is_up() as u8)];
+    if row.is_timed() {
+        lines.push(format!("{}.response_time:{}|ms", base, row.time_ms));
+    }
+    if let Ok(code) = row.status.parse::<u16>() {
+        lines.push(format!("{}.status.{}:1|c", base, code));
+    }
+    if let Some(category) = category::of(row) {
+        lines.push(format!("{}.errors.{}:1|c", base, category.name()));
+    }
+    lines
+}
+
+/// Packs StatsD lines into newline-separated datagrams of at most MAX_DATAGRAM bytes
+fn datagrams(lines: &[String]) -> Vec<String> {
+    let mut datagrams: Vec<String> = Vec::new();
+    for line in lines {
+        match datagrams.last_mut() {
+            Some(last) if last.len() + 1 + line.len() <= MAX_DATAGRAM => {
+                last.push('\n');
+                last.push_str(line);
+            }
+            _ => datagrams.push(line.clone()),
+        }
+    }
+    datagrams
+}
+
+/// Encodes a row as Graphite plaintext lines under `PREFIX.KEY`: `up`, `status_code`,
+/// `response_time_ms` (responses only), `cert_expiry` and `errors.CATEGORY` for failures
+fn graphite_lines(row: &ResultRow, prefix: &str) -> Vec<String> {
+    let base = format!("{}.{}", prefix, metric_key(row));
+    let time = parse_timestamp(&row.timestamp).unwrap_or_else(chrono::Utc::now).timestamp();
+    let mut lines = vec![
+        format!("{}.up {} {}", base, row.is_up() as u8, time),
+        format!("{}.status_code {} {}", base, row.status.parse::<u16>().unwrap_or(0), time),
+    ];
+    if row.is_timed() {
+        lines.push(format!("{}.response_time_ms {} {}", base, row.time_ms, time));
+    }
+    if let Some(expires) = parse_timestamp(&row.cert_expires) {
+        lines.push(format!("{}.cert_expiry {} {}", base, expires.timestamp(), time));
+    }
+    if let Some(category) = category::of(row) {
+        lines.push(format!("{}.errors.{} 1 {}", base, category.name(), time));
+    }
+    lines
+}
+
+/// Dotted metric path of a row: `HOST_PORT.PATH` for URLs (e.g. `api_example_com.v1_health`),
+/// `scenario.NAME[.STEP]` for scenario rows
+fn metric_key(row: &ResultRow) -> String {
+    if !row.scenario.is_empty() {
+        let mut key = format!("scenario.{}", sanitize(&row.scenario));
+        if !row.step.is_empty() {
+            key.push('.');
+            key.push_str(&sanitize(&row.step));
+        }
+        return key;
+    }
+    match reqwest::Url::parse(&row.url) {
+        Ok(url) => {
+            let mut key = sanitize(url.host_str().unwrap_or("unknown"));
+            if let Some(port) = url.port() {
+                key.push_str(&format!("_{}", port));
+            }
+            let path = format!("{}{}", url.path(), url.query().map(|q| format!("?{}", q)).unwrap_or_default());
+            let path = sanitize(path.trim_matches('/'));
+            if !path.is_empty() {
+                key.push('.');
+                key.push_str(&path);
+            }
+            key
+        }
+        Err(_) => sanitize(&row.url),
+    }
+}
+
+/// Replaces every character that is not allowed in a metric path segment with `_`
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(url: &str, status: &str) -> ResultRow {
+        ResultRow {
+            url: url.to_string(),
+            status: status.to_string(),
+            time_ms: 120,
+            size_bytes: 5,
+            timestamp: "2026-10-18 12:00:00 UTC".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn influx_escapes_tags_and_fields() {
+        let row = ResultRow {
+            tags: "shop,prod".to_string(),
+            reason: "said \"hi\"\nand left".to_string(),
+            cert_expires: "2027-01-01 00:00:00 UTC".to_string(),
+            ..row("https://example.com/a b?x=1,2", "200")
+        };
+        let extra = BTreeMap::from([("region name".to_string(), "eu=west".to_string())]);
+        assert_eq!(
+            influx_line(&row, "url checker,x", &extra),
+            "url\\ checker\\,x,host=example.com,region\\ name=eu\\=west,status=200,tags=shop\\,prod,\
+             url=https://example.com/a\\ b?x\\=1\\,2 up=true,status_code=200i,response_time_ms=120i,size_bytes=5i,\
+             cert_expiry=1798761600i,reason=\"said \\\"hi\\\" and left\" 1792324800000000000"
+        );
+    }
+
+    #[test]
+    fn influx_failures_carry_a_category_and_no_time() {
+        let row = ResultRow { reason: "C:\\path".to_string(), ..row("not a url", "ERROR") };
+        let line = influx_line(&row, DEFAULT_PREFIX, &BTreeMap::new());
+        assert_eq!(
+            line,
+            "url_checker,category=other,status=ERROR,url=not\\ a\\ url \
+             up=false,status_code=0i,size_bytes=5i,reason=\"C:\\\\path\" 1792324800000000000"
+        );
+    }
+
+    #[test]
+    fn statsd_metrics() {
+        let lines = statsd_lines(&row("https://example.com:8443/a b?x=1,2", "503"), "checks");
+        assert_eq!(
+            lines,
+            [
+                "checks.example_com_8443.a_20b_x_1_2.checks:1|c",
+                "checks.example_com_8443.a_20b_x_1_2.up:0|g",
+                "checks.example_com_8443.a_20b_x_1_2.response_time:120|ms",
+                "checks.example_com_8443.a_20b_x_1_2.status.503:1|c",
+                "checks.example_com_8443.a_20b_x_1_2.errors.http_5xx:1|c",
+            ]
+        );
+
+        let lines: Vec<String> = (0..100).map(|i| format!("{}.checks:1|c", "x".repeat(i % 40))).collect();
+        let packed = datagrams(&lines);
+        assert!(packed.len() > 1);
+        assert!(packed.iter().all(|d| d.len() <= MAX_DATAGRAM));
+        assert_eq!(packed.join("\n"), lines.join("\n"));
+    }
+
+    #[test]
+    fn graphite_metrics() {
+        let home = ResultRow { cert_expires: "2027-01-01 00:00:00 UTC".to_string(), ..row("https://Example.com/", "200") };
+        assert_eq!(
+            graphite_lines(&home, DEFAULT_PREFIX),
+            [
+                "url_checker.example_com.up 1 1792324800",
+                "url_checker.example_com.status_code 200 1792324800",
+                "url_checker.example_com.response_time_ms 120 1792324800",
+                "url_checker.example_com.cert_expiry 1798761600 1792324800",
+            ]
+        );
+
+        let step = ResultRow { scenario: "check out".to_string(), step: "pay.now".to_string(), ..row("https://example.com", "200") };
+        assert_eq!(metric_key(&step), "scenario.check_out.pay_now");
+    }
+
+    #[test]
+    fn sink_specs() {
+        let spec: SinkSpec = "influxdb=http://localhost:8086/api/v2/write?bucket=b".parse().unwrap();
+        assert_eq!(spec.kind, SinkKind::Influx);
+        assert!(spec.is_http());
+        assert!("influx=metrics.lp".parse::<SinkSpec>().is_ok());
+        assert!("statsd=localhost:8125".parse::<SinkSpec>().is_ok());
+
+        for invalid in ["statsd=localhost", "graphite=", "prometheus=localhost:9090", "localhost:2003", "influx=http://"] {
+            assert!(invalid.parse::<SinkSpec>().is_err(), "{}", invalid);
+        }
+    }
+}
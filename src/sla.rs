@@ -0,0 +1,372 @@
+// Uptime, outage and error-budget reporting over the stored check history
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::Args as ClapArgs;
+use colored::*;
+use serde::Serialize;
+
+use crate::history::{History, StoredResult};
+use crate::timeouts;
+
+/// Options of the `report` subcommand
+#[derive(ClapArgs, Debug)]
+pub struct ReportArgs {
+    /// Window ending now to report on (repeatable, e.g. 24h, 7d, 30d)
+    #[arg(long, value_parser = timeouts::parse_duration, default_values = ["24h", "7d", "30d"])]
+    pub window: Vec<Duration>,
+
+    /// Start of a custom window (RFC 3339 time or YYYY-MM-DD), reported instead of --window
+    #[arg(long, value_parser = parse_time)]
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the custom window (default: now)
+    #[arg(long, value_parser = parse_time, requires = "from")]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Service level objective as uptime percentage, for the error budget
+    #[arg(long, default_value_t = 99.9)]
+    pub slo: f64,
+
+    /// Group results by URL or by tag
+    #[arg(long, value_enum, default_value = "url")]
+    pub by: GroupBy,
+
+    /// Output format: table, csv or json
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: ReportFormat,
+}
+
+/// What report lines are computed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    Url, // One line per URL (and scenario)
+    Tag, // One line per tag, over all URLs carrying it
+}
+
+/// Output format of the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    Table, // Colored terminal table
+    Csv,   // CSV on stdout
+    Json,  // JSON array on stdout
+}
+
+/// Availability figures of one URL or tag over one window
+#[derive(Debug, Serialize)]
+pub struct ReportLine {
+    pub key: String,                    // URL (with what it was pinned to when split), "scenario:<name>" or tag
+    pub window: String,                 // Window label ("24h", "7d", or "custom")
+    pub from: String,                   // Window start (RFC 3339)
+    pub until: String,                  // Window end (RFC 3339)
+    pub checks: usize,                  // Checks in the window
+    pub up: usize,                      // Successful checks
+    pub uptime_pct: Option<f64>,        // Share of successful checks, None without checks
+    pub outages: usize,                 // Runs of consecutive failed checks
+    pub ongoing: bool,                  // Whether the last outage is still going on
+    pub downtime_secs: i64,             // Time from the first failed check of each outage to its recovery
+    pub mttr_secs: Option<i64>,         // Mean time to recovery of resolved outages
+    pub slo_pct: f64,                   // Objective the budget is computed against
+    pub budget_remaining_pct: Option<f64>, // Share of the allowed failed checks still unused (negative when blown)
+}
+
+/// An outage: consecutive failed checks of one URL
+struct Outage {
+    duration_secs: i64, // First failure until recovery (or window end when ongoing)
+    resolved: bool,     // Whether a successful check ended it
+}
+
+/// Parses an RFC 3339 time or a plain date (midnight UTC)
+fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .with_context(|| format!("invalid time '{}' (expected RFC 3339 or YYYY-MM-DD)", s))?;
+    Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+}
+
+/// Runs the `report` subcommand
+///
+/// # Arguments
+/// * `path` - History database
+/// * `report` - Windows, SLO, grouping and output format
+///
+/// # Returns
+/// * `Result<()>` - Ok or a database/output error
+pub fn run(path: &str, report: &ReportArgs) -> Result<()> {
+    if !(0.0..100.0).contains(&report.slo) {
+        bail!("--slo must be a percentage below 100, got {}", report.slo);
+    }
+    let now = Utc::now();
+    let windows: Vec<(String, DateTime<Utc>, DateTime<Utc>)> = match report.from {
+        Some(from) => vec![("custom".to_string(), from, report.until.unwrap_or(now))],
+        None => report
+            .window
+            .iter()
+            .map(|w| Ok((timeouts::format_duration(*w), now - chrono::Duration::from_std(*w)?, now)))
+            .collect::<Result<_>>()?,
+    };
+    let earliest = windows.iter().map(|(_, from, _)| *from).min().unwrap_or(now);
+
+    let history = History::open(path)?;
+    // Scenario steps are covered by their scenario's summary row; skipped checks say nothing
+    let results: Vec<StoredResult> = history
+        .results(None, Some(earliest), None)?
+        .into_iter()
+        .filter(|r| r.row.step.is_empty() && r.row.status != "SKIPPED")
+        .collect();
+
+    let mut lines = Vec::new();
+    for (label, from, until) in &windows {
+        let in_window: Vec<&StoredResult> = results.iter().filter(|r| r.checked_at >= *from && r.checked_at < *until).collect();
+        let by_url = by_check(&in_window);
+
+        match report.by {
+            GroupBy::Url => {
+                for (url, series) in &by_url {
+                    lines.push(line(url, label, *from, *until, &[series.as_slice()], report.slo));
+                }
+            }
+            GroupBy::Tag => {
+                let mut by_tag: BTreeMap<&str, Vec<&Vec<&StoredResult>>> = BTreeMap::new();
+                for series in by_url.values() {
+                    let tags = series.last().map(|r| r.row.tags.as_str()).unwrap_or("");
+                    for tag in tags.split(',').filter(|t| !t.is_empty()) {
+                        by_tag.entry(tag).or_default().push(series);
+                    }
+                }
+                for (tag, series) in &by_tag {
+                    let series: Vec<&[&StoredResult]> = series.iter().map(|s| s.as_slice()).collect();
+                    lines.push(line(tag, label, *from, *until, &series, report.slo));
+                }
+            }
+        }
+    }
+
+    match report.format {
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&lines)?),
+        ReportFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(std::io::stdout());
+            for line in &lines {
+                wtr.serialize(line)?;
+            }
+            wtr.flush()?;
+        }
+        ReportFormat::Table => print_table(&lines, report.by),
+    }
+    Ok(())
+}
+
+/// Groups results into the series of every check, oldest first
+/// URLs checked per address family (`--dual-stack`) or per address (`--each-address`) get a
+/// series for each, so one family's failures don't interleave with the other's successes.
+/// The split goes by what the check was pinned to rather than the connection it made, so
+/// failed connections and DNS changes stay in the URL's series
+///
+/// # Arguments
+/// * `results` - Results of one window, oldest first
+///
+/// # Returns
+/// * `BTreeMap<String, Vec<&StoredResult>>` - Results per report key
+fn by_check<'a>(results: &[&'a StoredResult]) -> BTreeMap<String, Vec<&'a StoredResult>> {
+    let mut pins: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for result in results {
+        pins.entry(&result.row.url).or_default().insert(&result.row.pinned_to);
+    }
+
+    let mut by_check: BTreeMap<String, Vec<&StoredResult>> = BTreeMap::new();
+    for result in results {
+        let row = &result.row;
+        let key = if pins[row.url.as_str()].len() > 1 && !row.pinned_to.is_empty() {
+            format!("{} [{}]", row.url, row.pinned_to)
+        } else {
+            row.url.clone()
+        };
+        by_check.entry(key).or_default().push(result);
+    }
+    by_check
+}
+
+/// Computes the figures of one URL or tag over one window
+///
+/// # Arguments
+/// * `key` - URL or tag
+/// * `label` - Window label
+/// * `from` - Window start
+/// * `until` - Window end
+/// * `series` - Results of each URL in the group, oldest first
+/// * `slo` - Objective in percent
+///
+/// # Returns
+/// * `ReportLine` - Figures for the report
+fn line(
+    key: &str,
+    label: &str,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    series: &[&[&StoredResult]],
+    slo: f64,
+) -> ReportLine {
+    let checks: usize = series.iter().map(|s| s.len()).sum();
+    let up: usize = series.iter().map(|s| s.iter().filter(|r| r.row.is_up()).count()).sum();
+    let outages: Vec<Outage> = series.iter().flat_map(|s| outages(s, until)).collect();
+
+    let resolved: Vec<i64> = outages.iter().filter(|o| o.resolved).map(|o| o.duration_secs).collect();
+    let failed = checks - up;
+    let allowed = checks as f64 * (100.0 - slo) / 100.0;
+
+    ReportLine {
+        key: key.to_string(),
+        window: label.to_string(),
+        from: from.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        until: until.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        checks,
+        up,
+        uptime_pct: (checks > 0).then(|| up as f64 / checks as f64 * 100.0),
+        outages: outages.len(),
+        ongoing: outages.iter().any(|o| !o.resolved),
+        downtime_secs: outages.iter().map(|o| o.duration_secs).sum(),
+        mttr_secs: (!resolved.is_empty()).then(|| resolved.iter().sum::<i64>() / resolved.len() as i64),
+        slo_pct: slo,
+        budget_remaining_pct: (checks > 0).then(|| (1.0 - failed as f64 / allowed) * 100.0),
+    }
+}
+
+/// Splits one URL's results into outages of consecutive failed checks
+fn outages(series: &[&StoredResult], until: DateTime<Utc>) -> Vec<Outage> {
+    let mut outages = Vec::new();
+    let mut started: Option<DateTime<Utc>> = None;
+    for result in series {
+        match (result.row.is_up(), started) {
+            (false, None) => started = Some(result.checked_at),
+            (true, Some(start)) => {
+                outages.push(Outage { duration_secs: (result.checked_at - start).num_seconds(), resolved: true });
+                started = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = started {
+        outages.push(Outage { duration_secs: (until - start).num_seconds(), resolved: false });
+    }
+    outages
+}
+
+/// Prints report lines as a colored table
+fn print_table(lines: &[ReportLine], by: GroupBy) {
+    let key_header = if by == GroupBy::Tag { "TAG" } else { "URL" };
+    println!("{:<44} {:<7} {:>7} {:>9} {:>8} {:>10} {:>9} {:>12}",
+        key_header.bold(),
+        "WINDOW".bold(),
+        "CHECKS".bold(),
+        "UPTIME".bold(),
+        "OUTAGES".bold(),
+        "DOWNTIME".bold(),
+        "MTTR".bold(),
+        "BUDGET LEFT".bold()
+    );
+    println!("{}", "─".repeat(115).bright_black());
+    for line in lines {
+        let key = if line.key.chars().count() > 42 {
+            format!("{}...", line.key.chars().take(39).collect::<String>())
+        } else {
+            line.key.clone()
+        };
+        let uptime = match line.uptime_pct {
+            Some(pct) if pct >= line.slo_pct => format!("{:.3}%", pct).green(),
+            Some(pct) => format!("{:.3}%", pct).red(),
+            None => "N/A".bright_black(),
+        };
+        let outages = if line.ongoing {
+            format!("{}*", line.outages).red()
+        } else {
+            line.outages.to_string().normal()
+        };
+        let budget = match line.budget_remaining_pct {
+            Some(pct) if pct >= 0.0 => format!("{:.1}%", pct).green(),
+            Some(pct) => format!("{:.1}%", pct).red().bold(),
+            None => "N/A".bright_black(),
+        };
+        let secs = |s: i64| timeouts::format_duration(Duration::from_secs(s.max(0) as u64));
+        println!("{:<44} {:<7} {:>7} {:>9} {:>8} {:>10} {:>9} {:>12}",
+            key,
+            line.window,
+            line.checks,
+            uptime,
+            outages,
+            secs(line.downtime_secs),
+            line.mttr_secs.map(secs).unwrap_or_else(|| "-".to_string()),
+            budget
+        );
+    }
+    println!("{}", "─".repeat(115).bright_black());
+    if lines.iter().any(|l| l.ongoing) {
+        println!("{}", "* outage still ongoing".bright_black());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResultRow;
+
+    /// A result of `run_id` checked `minutes` after the first one
+    fn result(run_id: i64, minutes: i64, pinned_to: &str, addr: &str, status: &str) -> StoredResult {
+        let family = if addr.is_empty() { "" } else if addr.starts_with('[') { "IPv6" } else { "IPv4" };
+        StoredResult {
+            run_id,
+            checked_at: DateTime::UNIX_EPOCH + chrono::Duration::minutes(minutes),
+            row: ResultRow {
+                url: "https://example.com".to_string(),
+                status: status.to_string(),
+                ip_family: family.to_string(),
+                remote_addr: addr.to_string(),
+                pinned_to: pinned_to.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn series(results: &[StoredResult]) -> BTreeMap<String, Vec<&StoredResult>> {
+        let results: Vec<&StoredResult> = results.iter().collect();
+        by_check(&results)
+    }
+
+    #[test]
+    fn dual_stack_rows_are_separate_series() {
+        // IPv6 is down throughout, IPv4 never is: one ongoing IPv6 outage, not one per cycle
+        let results = [
+            result(1, 0, "IPv4", "192.0.2.1:443", "200"),
+            result(1, 0, "IPv6", "", "ERROR"),
+            result(1, 1, "IPv4", "192.0.2.1:443", "200"),
+            result(1, 1, "IPv6", "", "ERROR"),
+        ];
+        let series = series(&results);
+        assert_eq!(series.keys().collect::<Vec<_>>(), ["https://example.com [IPv4]", "https://example.com [IPv6]"]);
+        let ipv6 = outages(&series["https://example.com [IPv6]"], Utc::now());
+        assert_eq!(ipv6.len(), 1);
+        assert!(!ipv6[0].resolved);
+        assert!(outages(&series["https://example.com [IPv4]"], Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn watch_cycles_of_one_run_stay_in_the_url_series() {
+        // A watch session records every cycle under one run: failed connections (no address)
+        // and DNS changes must not split the URL
+        let results = [
+            result(1, 0, "", "192.0.2.1:443", "200"),
+            result(1, 1, "", "", "ERROR"),
+            result(1, 2, "", "192.0.2.2:443", "200"),
+            result(1, 3, "", "[2001:db8::1]:443", "200"),
+        ];
+        let series = series(&results);
+        assert_eq!(series.keys().collect::<Vec<_>>(), ["https://example.com"]);
+
+        let line = line("https://example.com", "1h", DateTime::UNIX_EPOCH, Utc::now(), &[&series["https://example.com"]], 99.9);
+        assert_eq!((line.checks, line.up, line.outages, line.ongoing), (4, 3, 1, false));
+        assert_eq!(line.downtime_secs, 60);
+    }
+}
@@ -0,0 +1,77 @@
+// {{variable}} templating for request URLs, headers and bodies
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+/// Renders `{{name}}` placeholders in a string
+/// Lookup order: `vars` (e.g. values extracted by earlier scenario steps), built-ins, then
+/// environment variables. `{{env.NAME}}` always reads the environment.
+///
+/// Built-ins:
+/// * `{{uuid}}` - a fresh random UUID (v4) per placeholder
+/// * `{{now}}` - current UTC time in RFC 3339 format
+/// * `{{timestamp}}` - current Unix time in seconds
+/// * `{{timestamp_ms}}` - current Unix time in milliseconds
+///
+/// # Arguments
+/// * `text` - Template text
+/// * `vars` - Variables taking precedence over built-ins and the environment
+///
+/// # Returns
+/// * `Result<String>` - Rendered text, or an error naming the first undefined variable
+pub fn render(text: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            bail!("unterminated '{{{{' in template");
+        };
+        let name = after[..end].trim();
+        out.push_str(&lookup(name, vars)?);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Renders every string inside a JSON document, leaving its structure intact
+/// so substituted values can never break the JSON syntax
+pub fn render_json(value: &serde_json::Value, vars: &HashMap<String, String>) -> Result<serde_json::Value> {
+    Ok(match value {
+        serde_json::Value::String(s) => serde_json::Value::String(render(s, vars)?),
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items.iter().map(|v| render_json(v, vars)).collect::<Result<_>>()?,
+        ),
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), render_json(v, vars)?)))
+                .collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+fn lookup(name: &str, vars: &HashMap<String, String>) -> Result<String> {
+    if let Some(var) = name.strip_prefix("env.") {
+        return match std::env::var(var) {
+            Ok(value) => Ok(value),
+            Err(_) => bail!("undefined environment variable '{}'", var),
+        };
+    }
+    if let Some(value) = vars.get(name) {
+        return Ok(value.clone());
+    }
+    let now = chrono::Utc::now();
+    match name {
+        "uuid" => Ok(uuid::Uuid::new_v4().to_string()),
+        "now" => Ok(now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        "timestamp" => Ok(now.timestamp().to_string()),
+        "timestamp_ms" => Ok(now.timestamp_millis().to_string()),
+        _ => match std::env::var(name) {
+            Ok(value) => Ok(value),
+            Err(_) => bail!("undefined template variable '{}'", name),
+        },
+    }
+}
@@ -0,0 +1,80 @@
+// Timeout settings: duration parsing for the CLI and structured input, and per-check limits
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Deserializer};
+
+/// Parses a duration such as `250ms`, `1.5s`, `2m`, `1h` or `7d`
+/// A bare number is taken as seconds, matching the historical `--timeout` unit
+///
+/// # Arguments
+/// * `s` - Duration string
+///
+/// # Returns
+/// * `Result<Duration>` - Parsed duration or an error
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("invalid duration '{}' (expected e.g. 500ms, 2s, 1.5m)", s))?;
+    let millis = match unit.trim() {
+        "ms" => value,
+        "" | "s" => value * 1_000.0,
+        "m" => value * 60_000.0,
+        "h" => value * 3_600_000.0,
+        "d" => value * 86_400_000.0,
+        other => return Err(anyhow!("unknown duration unit '{}' in '{}'", other, s)),
+    };
+    Ok(Duration::from_micros((millis * 1_000.0).round() as u64))
+}
+
+/// Formats a duration compactly for display (e.g., "500ms", "10s", "1m 30s", "7d")
+pub fn format_duration(d: Duration) -> String {
+    let ms = d.as_millis();
+    let secs = ms / 1_000;
+    if ms % 1_000 != 0 {
+        format!("{}ms", ms)
+    } else if secs < 60 {
+        format!("{}s", secs)
+    } else if secs % 86_400 == 0 {
+        format!("{}d", secs / 86_400)
+    } else if secs % 3_600 == 0 {
+        format!("{}h", secs / 3_600)
+    } else if secs % 60 == 0 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
+
+/// Deserializes an optional duration from structured input
+/// Accepts the same strings as the CLI, or a number of seconds
+pub fn deserialize_opt<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Seconds(f64),
+        Text(String),
+    }
+
+    match Option::<Raw>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Raw::Seconds(secs)) if secs >= 0.0 => Ok(Some(Duration::from_secs_f64(secs))),
+        Some(Raw::Seconds(secs)) => Err(serde::de::Error::custom(format!("negative duration {}", secs))),
+        Some(Raw::Text(text)) => parse_duration(&text).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Per-check time limits applied around a single request
+/// The connect timeout lives on the client, since reqwest only supports it there
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    pub total: Duration,               // Whole request including the body
+    pub first_byte: Option<Duration>,  // Until response headers arrive
+    pub read: Option<Duration>,        // Maximum idle gap while reading the body
+}
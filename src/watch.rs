@@ -0,0 +1,435 @@
+// Watch mode: re-checks the URLs on an interval, keeping the latest result per check in memory
+use std::io::{IsTerminal, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use clap::Args as ClapArgs;
+use colored::*;
+use futures::stream::{self, StreamExt};
+use tokio::time::Instant;
+
+use crate::alert::{self, Alerter, Thresholds, Webhook};
+use crate::email::Mailer;
+use crate::history::Recorder;
+use crate::otel::Telemetry;
+use crate::output::{self, Decor, ReportSpec};
+use crate::prometheus::{self, Metrics};
+use crate::scenario::Scenario;
+use crate::session::Session;
+use crate::sinks::Sinks;
+use crate::alert::Event;
+use crate::{
+    classify, dual_stack_mismatches, hooks, ndjson, run_complete_hook, run_job, run_scenarios, timeouts, Args, Format, Job,
+    ResultRow, Stats,
+};
+
+/// Options of the `watch` subcommand
+#[derive(ClapArgs, Debug)]
+pub struct WatchArgs {
+    /// Time between two checks of a URL; structured input can override it per URL with "interval"
+    #[arg(long, default_value = "60", value_parser = timeouts::parse_duration)]
+    pub interval: Duration,
+
+    /// Maximum random delay before each URL's first check, spreading checks over time
+    #[arg(long, default_value = "0", value_parser = timeouts::parse_duration)]
+    pub jitter: Duration,
+
+    /// Stop after this many cycles instead of running until interrupted
+    #[arg(long)]
+    pub cycles: Option<usize>,
+
+    /// Webhook to notify when a URL goes down or recovers, as [FORMAT=]URL (repeatable)
+    /// FORMAT is json (default), slack, teams or discord
+    #[arg(long, value_name = "[FORMAT=]URL")]
+    pub webhook: Vec<Webhook>,
+
+    /// Consecutive failures before a URL counts as down
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub alert_after: u32,
+
+    /// Consecutive successes before a down URL counts as recovered
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub recover_after: u32,
+
+    /// Additional delivery attempts when a webhook fails (with exponential backoff)
+    #[arg(long, default_value_t = 3)]
+    pub webhook_retries: u32,
+
+    /// Serve Prometheus metrics at http://ADDR/metrics while watching (e.g. 0.0.0.0:9899)
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_listen: Option<SocketAddr>,
+}
+
+/// Where results go besides the reports
+#[derive(Clone, Copy)]
+pub struct Destinations<'a> {
+    pub mailer: Option<&'a Mailer>,       // Mails state changes to the configured recipients, if set
+    pub sinks: &'a Sinks,                 // Metric sinks each cycle's rows are sent to
+    pub telemetry: Option<&'a Telemetry>, // OpenTelemetry export after each cycle, if enabled
+}
+
+/// Number of recent alerts shown below the live summary
+const RECENT_ALERTS: usize = 5;
+
+/// Re-checks jobs and scenarios until interrupted (Ctrl-C) or `--cycles` is reached
+/// A cycle runs every check that is due; its rows are appended to the output file
+/// and the live summary is redrawn with the latest result of every check
+///
+/// # Arguments
+/// * `args` - Command-line arguments (concurrency, deadline per cycle, output settings)
+/// * `watch` - Interval, jitter and cycle limit
+/// * `session` - Shared cookie jar and login token
+/// * `destinations` - Email recipients, metric sinks and telemetry export
+/// * `jobs` - Planned URL checks
+/// * `scenarios` - Scenarios, run on the global interval
+///
+/// # Returns
+/// * `Result<()>` - Ok when stopped, or an error writing the output
+pub async fn run(
+    args: &Args,
+    watch: &WatchArgs,
+    session: &Session,
+    destinations: &Destinations<'_>,
+    jobs: Vec<Job>,
+    scenarios: Vec<Scenario>,
+) -> Result<()> {
+    let Destinations { mailer, sinks, telemetry } = *destinations;
+    if watch.interval.is_zero() || jobs.iter().any(|job| job.interval.is_some_and(|i| i.is_zero())) {
+        bail!("Watch interval must be greater than zero");
+    }
+    let reports = args.reports()?;
+
+    // Jitter only delays the first check; afterwards each check keeps its own rhythm
+    let start = Instant::now();
+    let jitter = || start + watch.jitter.mul_f64(fastrand::f64());
+    let mut due: Vec<Instant> = jobs.iter().map(|_| jitter()).collect();
+    let mut scenarios_due = (!scenarios.is_empty()).then(jitter);
+
+    let mut latest: Vec<Option<ResultRow>> = vec![None; jobs.len()];
+    let mut latest_scenarios: Vec<ResultRow> = Vec::new();
+    let mut cycle = 0;
+
+    // State changes are always tracked for the live view, and delivered to webhooks and email recipients
+    let mut alerter = Alerter::new(Thresholds { failures: watch.alert_after, recoveries: watch.recover_after });
+    let webhook_client = reqwest::Client::builder()
+        .user_agent("url-checker/0.2")
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    let mut recent: Vec<String> = Vec::new();
+
+    // The whole watch session is one run in the history
+    let mut recorder = Recorder::start(args, "watch")?;
+
+    // Metrics accumulate over the session for the /metrics endpoint and Prometheus reports
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+    let _server = match watch.metrics_listen {
+        Some(addr) => {
+            let listener = prometheus::listen(addr).await?;
+            Some(AbortOnDrop(tokio::spawn(prometheus::serve(listener, metrics.clone()))))
+        }
+        None => None,
+    };
+
+    let interrupt = tokio::signal::ctrl_c();
+    tokio::pin!(interrupt);
+
+    while let Some(next) = due.iter().copied().chain(scenarios_due).min() {
+        tokio::select! {
+            _ = tokio::time::sleep_until(next) => {}
+            _ = &mut interrupt => break,
+        }
+
+        // Everything due now runs in this cycle
+        let now = Instant::now();
+        let ready: Vec<usize> = (0..jobs.len()).filter(|&i| due[i] <= now).collect();
+        let run_scenarios_now = scenarios_due.is_some_and(|d| d <= now);
+        let deadline = args.deadline.map(|d| now + d);
+
+        let checks = stream::iter(ready.iter().map(|&i| {
+            let job = &jobs[i];
+            async move { (i, run_job(job, deadline).await) }
+        }))
+        .buffer_unordered(args.concurrency)
+        .collect::<Vec<_>>();
+        let cycle_run = async {
+            let checked = checks.await;
+            let scenario_rows = if run_scenarios_now {
+                Some(run_scenarios(args, session, &scenarios, deadline).await)
+            } else {
+                None
+            };
+            (checked, scenario_rows)
+        };
+        let (mut checked, scenario_rows) = tokio::select! {
+            result = cycle_run => result,
+            _ = &mut interrupt => break,
+        };
+        cycle += 1;
+
+        // Keep input order in the output, then schedule the next check of each job
+        checked.sort_by_key(|(i, _)| *i);
+        let mut rows = Vec::with_capacity(checked.len());
+        let mut alerts = Vec::new();
+        for (i, row) in checked {
+            due[i] = next_due(due[i], jobs[i].interval.unwrap_or(watch.interval));
+            alerts.extend(alerter.observe(&format!("job:{}", i), &row));
+            latest[i] = Some(row.clone());
+            rows.push(row);
+        }
+        if let Some(scenario_rows) = scenario_rows {
+            let scenario_rows = scenario_rows?;
+            scenarios_due = scenarios_due.map(|d| next_due(d, watch.interval));
+            for row in scenario_rows.iter().filter(|r| r.is_scenario_summary()) {
+                alerts.extend(alerter.observe(&row.url, row));
+            }
+            latest_scenarios = scenario_rows.clone();
+            rows.extend(scenario_rows);
+        }
+
+        metrics.lock().expect("metrics lock poisoned").observe(&rows);
+        append_results(args, &reports, cycle, &rows, &metrics)?;
+        if let Some(recorder) = &mut recorder {
+            recorder.record(&rows)?;
+        }
+
+        if !alerts.is_empty() {
+            let time = chrono::Utc::now().format("%H:%M:%S");
+            recent.extend(alerts.iter().map(|a| format!("{} {}", time, alert::summary(a))));
+            let failed = alert::deliver(&webhook_client, &watch.webhook, &alerts, watch.webhook_retries).await;
+            recent.extend(failed.into_iter().map(|e| format!("{} ⚠ {}", time, e)));
+            if let Some(mailer) = mailer {
+                let failed = mailer.send_alerts(&alerts).await;
+                recent.extend(failed.into_iter().map(|e| format!("{} ⚠ {}", time, e)));
+            }
+            if let Some(command) = &args.on_failure {
+                let down = alerts.iter().filter(|a| a.event == Event::Down).map(|a| &a.result).collect::<Vec<_>>();
+                let failed = hooks::on_failure(command, &down, args.concurrency, args.hook_timeout).await;
+                recent.extend(failed.into_iter().map(|e| format!("{} ⚠ failure hook: {}", time, e)));
+            }
+        }
+        if !sinks.is_empty() {
+            let time = chrono::Utc::now().format("%H:%M:%S");
+            recent.extend(sinks.emit(&rows).await.into_iter().map(|e| format!("{} ⚠ {}", time, e)));
+        }
+        if let Some(telemetry) = telemetry {
+            let time = chrono::Utc::now().format("%H:%M:%S");
+            let failed = telemetry.export(&Stats::from_rows(&rows), args.apdex_threshold).await;
+            recent.extend(failed.into_iter().map(|e| format!("{} ⚠ {}", time, e)));
+        }
+        if let Some(command) = &args.on_complete {
+            if let Err(e) = run_complete_hook(command, args, &Stats::from_rows(&rows), &rows, Some(cycle)).await {
+                let time = chrono::Utc::now().format("%H:%M:%S");
+                recent.push(format!("{} ⚠ completion hook: {:#}", time, e));
+            }
+        }
+        let excess = recent.len().saturating_sub(RECENT_ALERTS);
+        recent.drain(..excess);
+
+        let next = due.iter().copied().chain(scenarios_due).min();
+        redraw(cycle, &latest, &latest_scenarios, &recent, next);
+
+        if watch.cycles.is_some_and(|max| cycle >= max) {
+            break;
+        }
+    }
+
+    // Persist cookies collected while watching
+    if let (Some(path), Some(jar)) = (&args.cookie_jar, &session.jar) {
+        jar.save(path)?;
+    }
+
+    say!(
+        "\n{} Stopped after {} cycle(s); results appended to {}",
+        "ℹ".cyan(),
+        cycle.to_string().bold(),
+        crate::report_paths(&reports).bright_white().bold()
+    );
+    Ok(())
+}
+
+/// Stops a spawned task when dropped, so the metrics server ends with the watch session
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Next time a check is due; a check that fell behind is rescheduled from now
+/// instead of being run several times in a row to catch up
+fn next_due(previous: Instant, interval: Duration) -> Instant {
+    let next = previous + interval;
+    let now = Instant::now();
+    if next < now {
+        now + interval
+    } else {
+        next
+    }
+}
+
+/// Appends one cycle's rows to every report
+/// CSV output gets a header only when the file is new or empty; JSON output gets
+/// one line per cycle holding the cycle number, time and its results; NDJSON output
+/// gets one line per result followed by a summary line for the cycle; Prometheus output
+/// is replaced with the metrics accumulated since watching started
+///
+/// # Arguments
+/// * `args` - Command-line arguments with the format options
+/// * `reports` - Reports to write
+/// * `cycle` - Number of the cycle, starting at 1
+/// * `rows` - Results of the cycle
+/// * `metrics` - Metrics of the whole session
+///
+/// # Returns
+/// * `Result<()>` - Ok or an error writing a report
+fn append_results(args: &Args, reports: &[ReportSpec], cycle: usize, rows: &[ResultRow], metrics: &Mutex<Metrics>) -> Result<()> {
+    for report in reports {
+        match report.format {
+            Format::Csv => {
+                let new_file = if report.is_stdout() {
+                    cycle == 1
+                } else {
+                    std::fs::metadata(&report.path).map(|m| m.len() == 0).unwrap_or(true)
+                };
+                let mut wtr = csv::WriterBuilder::new()
+                    .has_headers(new_file)
+                    .from_writer(output::append(&report.path)?);
+                for row in rows {
+                    wtr.serialize(row)?;
+                }
+                wtr.flush()?;
+            }
+            Format::Json => {
+                let line = serde_json::json!({
+                    "cycle": cycle,
+                    "generated_at": chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                    "results": rows,
+                });
+                writeln!(output::append(&report.path)?, "{}", serde_json::to_string(&line)?)?;
+            }
+            Format::Ndjson => {
+                let mut out = ndjson::Writer::append(&report.path)?;
+                for row in rows {
+                    out.result(row)?;
+                }
+                let mut metadata = Stats::from_rows(rows).to_json(args.apdex_threshold);
+                metadata["cycle"] = cycle.into();
+                metadata["generated_at"] = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string().into();
+                out.summary(&metadata)?;
+            }
+            // JUnit, HTML, Markdown and template documents cannot be appended to, so they always hold the latest cycle
+            Format::Junit | Format::Html | Format::Markdown | Format::Template => {
+                let mismatches = if args.dual_stack { dual_stack_mismatches(rows) } else { Vec::new() };
+                output::write(report, args, &Stats::from_rows(rows), &mismatches, rows)?;
+            }
+            Format::Prometheus => {
+                let exposition = metrics.lock().expect("metrics lock poisoned").render();
+                prometheus::write_textfile(&report.path, &exposition)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Redraws the live summary with the latest result of every check
+/// On a terminal the screen is cleared first; otherwise each summary is printed below the last
+///
+/// # Arguments
+/// * `cycle` - Number of completed cycles
+/// * `latest` - Latest result of each URL check, None if not checked yet
+/// * `scenarios` - Rows of the latest scenario run
+/// * `recent` - Most recent state changes and delivery failures
+/// * `next` - When the next check is due
+fn redraw(
+    cycle: usize,
+    latest: &[Option<ResultRow>],
+    scenarios: &[ResultRow],
+    recent: &[String],
+    next: Option<Instant>,
+) {
+    // Clear the screen and move the cursor home when drawing to a terminal
+    match output::decor() {
+        Decor::Off => return,
+        Decor::Stdout if std::io::stdout().is_terminal() => print!("\x1b[2J\x1b[H"),
+        Decor::Stderr if std::io::stderr().is_terminal() => eprint!("\x1b[2J\x1b[H"),
+        _ => {}
+    }
+
+    let rows = latest.iter().flatten().chain(scenarios.iter().filter(|r| r.is_scenario_summary()));
+    let (mut up, mut down, mut pending) = (0, 0, 0);
+    for row in latest.iter() {
+        match row {
+            Some(row) if row.is_up() => up += 1,
+            Some(_) => down += 1,
+            None => pending += 1,
+        }
+    }
+    for row in scenarios.iter().filter(|r| r.is_scenario_summary()) {
+        if row.is_up() {
+            up += 1;
+        } else {
+            down += 1;
+        }
+    }
+
+    say!("{}", "═".repeat(100).bright_blue().bold());
+    say!("{}", "  URL CHECKER - Watch Mode".bright_cyan().bold());
+    say!("{}", "═".repeat(100).bright_blue().bold());
+    let next_in = next
+        .map(|n| timeouts::format_duration(Duration::from_secs(n.saturating_duration_since(Instant::now()).as_secs())))
+        .unwrap_or_else(|| "-".to_string());
+    say!(
+        "{} Cycle {} at {}  {}  {}  {}  next check in {}",
+        "•".bright_cyan(),
+        cycle.to_string().bold(),
+        chrono::Utc::now().format("%H:%M:%S UTC"),
+        format!("{} up", up).green().bold(),
+        format!("{} down", down).red().bold(),
+        format!("{} pending", pending).bright_black(),
+        next_in.bright_white()
+    );
+
+    say!("{}", "─".repeat(100).bright_black());
+    say!("{:<50} {:<8} {:<10} {:<24} {}",
+        "URL".bold(),
+        "STATUS".bold(),
+        "TIME (ms)".bold(),
+        "CHECKED".bold(),
+        "RESULT".bold()
+    );
+    say!("{}", "─".repeat(100).bright_black());
+    for row in rows {
+        let url_display = if row.url.chars().count() > 48 {
+            format!("{}...", row.url.chars().take(45).collect::<String>())
+        } else {
+            row.url.clone()
+        };
+        let (status_color, status_icon, result_text) = classify(row);
+        let time = if row.status == "ERROR" || row.status == "SKIPPED" {
+            "N/A".bright_black()
+        } else {
+            row.time_ms.to_string().bright_white()
+        };
+        say!("{:<50} {:<8} {:<10} {:<24} {} {}",
+            url_display,
+            status_color,
+            time,
+            row.timestamp.bright_black(),
+            status_icon,
+            result_text
+        );
+    }
+    say!("{}", "─".repeat(100).bright_black());
+    if !recent.is_empty() {
+        say!("{} {}", "🔔".yellow(), "RECENT ALERTS".yellow().bold());
+        for line in recent {
+            say!("{} {}", "  •".bright_cyan(), line);
+        }
+        say!("{}", "─".repeat(100).bright_black());
+    }
+    say!("{}", "Press Ctrl-C to stop".bright_black());
+    let _ = std::io::stdout().flush();
+}